@@ -4,12 +4,15 @@ use ::rand::{RngCore, thread_rng};
 use macroquad::prelude::*;
 use thunders::{
     api::schema::json::Json,
-    client::{ThundersClient, ThundersClientBuilder, protocol::ws::WebSocketClientProtocol},
+    client::{
+        ThundersClient, ThundersClientBuilder,
+        protocol::{ClientProtocol, memory::MemoryClientProtocol, ws::WebSocketClientProtocol},
+    },
     server::{
         ThundersServer,
-        context::PlayerContext,
+        context::{PlayerContext, RoomContext},
         hooks::{Diff, GameHooks},
-        protocol::ws::WebSocketProtocol,
+        protocol::{NetworkProtocol, memory::MemoryProtocol, ws::WebSocketProtocol},
         runtime::sync::{Settings, SyncRuntime},
     },
 };
@@ -24,6 +27,7 @@ const PLATFORM_HEIGHT: f32 = 0.2;
 const DELTA: f32 = 0.016;
 
 const SERVER_MODE: &str = "server";
+const LOCAL_MODE: &str = "local";
 const LOBBY_TYPE: &str = "arkanoid";
 const LOBBY_ID: &str = "arkanoid_1";
 
@@ -32,13 +36,18 @@ async fn main() {
     let args = env::args().collect::<Vec<String>>();
     let mode = args
         .get(1)
-        .expect("Add server or client mode as argument. Example: -- client");
+        .expect("Add server, local or client mode as argument. Example: -- client");
 
     let client = if mode == SERVER_MODE {
-        start_server();
-        start_client(true)
+        start_server(WebSocketProtocol::new("127.0.0.1", 8080));
+        start_client(WebSocketClientProtocol::new("127.0.0.1", 8080), true)
+    } else if mode == LOCAL_MODE {
+        let protocol = MemoryProtocol::new();
+        let client_protocol = MemoryClientProtocol::new(protocol.connector());
+        start_server(protocol);
+        start_client(client_protocol, true)
     } else {
-        start_client(false)
+        start_client(WebSocketClientProtocol::new("127.0.0.1", 8080), false)
     };
 
     set_camera(&Camera2D {
@@ -108,8 +117,8 @@ async fn main() {
     }
 }
 
-fn start_server() {
-    thread::spawn(|| {
+fn start_server<N: NetworkProtocol + Send + 'static>(protocol: N) {
+    thread::spawn(move || {
         let rt = Builder::new_multi_thread()
             .worker_threads(1)
             .enable_all()
@@ -118,12 +127,13 @@ fn start_server() {
             .expect("failed to build Tokio runtime");
 
         rt.block_on(async {
-            let _ = ThundersServer::new(WebSocketProtocol::new("127.0.0.1", 8080), Json::default())
+            let _ = ThundersServer::new(protocol, Json::default())
                 .register::<SyncRuntime<_>, ArkanoidServer>(
                     LOBBY_TYPE,
                     Settings {
                         tick_no_action_millis: (DELTA * 1000.0) as u64,
                         tick_millis: (DELTA * 1000.0) as u64,
+                        ..Default::default()
                     },
                 )
                 .run()
@@ -132,7 +142,10 @@ fn start_server() {
     });
 }
 
-fn start_client(create_game: bool) -> ThundersClient<Json> {
+fn start_client<P: ClientProtocol + Send + 'static>(
+    protocol: P,
+    create_game: bool,
+) -> ThundersClient<Json> {
     let (tx, rx) = mpsc::sync_channel::<ThundersClient<Json>>(0);
     thread::spawn(move || {
         let rt = Builder::new_multi_thread()
@@ -143,14 +156,11 @@ fn start_client(create_game: bool) -> ThundersClient<Json> {
             .expect("failed to build Tokio runtime");
 
         rt.block_on(async {
-            let client = ThundersClientBuilder::new(
-                WebSocketClientProtocol::new("127.0.0.1", 8080),
-                Json::default(),
-            )
-            .register(LOBBY_TYPE)
-            .build()
-            .await
-            .unwrap();
+            let client = ThundersClientBuilder::new(protocol, Json::default())
+                .register(LOBBY_TYPE)
+                .build()
+                .await
+                .unwrap();
 
             let player_id: u64 = thread_rng().next_u64();
             client
@@ -226,6 +236,7 @@ impl thunders::server::hooks::GameHooks for ArkanoidServer {
     type Options = ();
     type Action = ArkanoidAction;
     type Delta = ArkanoidDiff;
+    type Event = ();
 
     fn build(options: Self::Options) -> Self {
         Self {
@@ -239,7 +250,11 @@ impl thunders::server::hooks::GameHooks for ArkanoidServer {
         }
     }
 
-    fn on_join(&mut self, player_cxt: &PlayerContext) -> Option<Vec<Diff<Self::Delta>>> {
+    fn on_join(
+        &mut self,
+        _room: &mut RoomContext,
+        player_cxt: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
         if self.platform_host_id.is_none() {
             self.platform_host_id = Some(player_cxt.id());
             self.platforms
@@ -273,7 +288,11 @@ impl thunders::server::hooks::GameHooks for ArkanoidServer {
         }
     }
 
-    fn on_leave(&mut self, player_cxt: &PlayerContext) -> Option<Diff<Self::Delta>> {
+    fn on_leave(
+        &mut self,
+        _room: &mut RoomContext,
+        player_cxt: &PlayerContext,
+    ) -> Option<Diff<Self::Delta>> {
         None
     }
 
@@ -287,6 +306,7 @@ impl thunders::server::hooks::GameHooks for ArkanoidServer {
 
     fn on_tick(
         &mut self,
+        _room: &mut RoomContext,
         player_cxts: &HashMap<u64, std::sync::Arc<PlayerContext>>,
         actions: Vec<(u64, Self::Action)>,
     ) -> Option<Vec<Diff<Self::Delta>>> {
@@ -3,13 +3,16 @@ use std::{collections::HashMap, env, sync::mpsc, thread, time::Duration};
 use ::rand::{RngCore, thread_rng};
 use macroquad::prelude::*;
 use thunders::{
-    api::schema::json::Json,
-    client::{ThundersClient, ThundersClientBuilder, protocol::ws::WebSocketClientProtocol},
+    api::{message::Delivery, schema::json::Json},
+    client::{
+        ThundersClient, ThundersClientBuilder,
+        protocol::{ClientProtocol, memory::MemoryClientProtocol, ws::WebSocketClientProtocol},
+    },
     server::{
         ThundersServer,
-        context::PlayerContext,
+        context::{PlayerContext, RoomContext},
         hooks::Diff,
-        protocol::ws::WebSocketProtocol,
+        protocol::{NetworkProtocol, memory::MemoryProtocol, ws::WebSocketProtocol},
         runtime::sync::{Settings, SyncRuntime},
     },
 };
@@ -22,6 +25,7 @@ const PLATFORM_HEIGHT: f32 = 3.;
 const DELTA: f32 = 0.016;
 
 const SERVER_MODE: &str = "server";
+const LOCAL_MODE: &str = "local";
 const LOBBY_TYPE: &str = "pong";
 const LOBBY_ID: &str = "pong_1";
 
@@ -30,13 +34,21 @@ async fn main() {
     let args = env::args().collect::<Vec<String>>();
     let mode = args
         .get(1)
-        .expect("Add server or client mode as argument. Example: -- client");
+        .expect("Add server, local or client mode as argument. Example: -- client");
 
     let client = if mode == SERVER_MODE {
-        start_server();
-        start_client(true)
+        start_server(WebSocketProtocol::new("127.0.0.1", 8080));
+        start_client(WebSocketClientProtocol::new("127.0.0.1", 8080), true)
+    } else if mode == LOCAL_MODE {
+        let protocol = MemoryProtocol::new();
+        let connector = protocol.connector();
+        start_server(protocol);
+        let client = start_client(MemoryClientProtocol::new(connector.clone()), true);
+        // The ball only moves with two players, the opponent stands still
+        start_client(MemoryClientProtocol::new(connector), false);
+        client
     } else {
-        start_client(false)
+        start_client(WebSocketClientProtocol::new("127.0.0.1", 8080), false)
     };
 
     set_camera(&Camera2D {
@@ -99,8 +111,8 @@ async fn main() {
     }
 }
 
-fn start_server() {
-    thread::spawn(|| {
+fn start_server<N: NetworkProtocol + Send + 'static>(protocol: N) {
+    thread::spawn(move || {
         let rt = Builder::new_multi_thread()
             .worker_threads(1)
             .enable_all()
@@ -109,12 +121,13 @@ fn start_server() {
             .expect("failed to build Tokio runtime");
 
         rt.block_on(async {
-            let _ = ThundersServer::new(WebSocketProtocol::new("127.0.0.1", 8080), Json::default())
+            let _ = ThundersServer::new(protocol, Json::default())
                 .register::<SyncRuntime<_>, PongServer>(
                     LOBBY_TYPE,
                     Settings {
                         tick_no_action_millis: (DELTA * 1000.0) as u64,
                         tick_millis: (DELTA * 1000.0) as u64,
+                        ..Default::default()
                     },
                 )
                 .run()
@@ -123,7 +136,10 @@ fn start_server() {
     });
 }
 
-fn start_client(create_game: bool) -> ThundersClient<Json> {
+fn start_client<P: ClientProtocol + Send + 'static>(
+    protocol: P,
+    create_game: bool,
+) -> ThundersClient<Json> {
     let (tx, rx) = mpsc::sync_channel::<ThundersClient<Json>>(0);
     thread::spawn(move || {
         let rt = Builder::new_multi_thread()
@@ -134,14 +150,11 @@ fn start_client(create_game: bool) -> ThundersClient<Json> {
             .expect("failed to build Tokio runtime");
 
         rt.block_on(async {
-            let client = ThundersClientBuilder::new(
-                WebSocketClientProtocol::new("127.0.0.1", 8080),
-                Json::default(),
-            )
-            .register(LOBBY_TYPE)
-            .build()
-            .await
-            .unwrap();
+            let client = ThundersClientBuilder::new(protocol, Json::default())
+                .register(LOBBY_TYPE)
+                .build()
+                .await
+                .unwrap();
 
             let player_id: u64 = thread_rng().next_u64();
             client
@@ -216,6 +229,7 @@ impl thunders::server::hooks::GameHooks for PongServer {
     type Options = ();
     type Action = PongAction;
     type Delta = PongDiff;
+    type Event = ();
 
     fn build(options: Self::Options) -> Self {
         Self {
@@ -229,7 +243,11 @@ impl thunders::server::hooks::GameHooks for PongServer {
         }
     }
 
-    fn on_join(&mut self, player_cxt: &PlayerContext) -> Option<Vec<Diff<Self::Delta>>> {
+    fn on_join(
+        &mut self,
+        _room: &mut RoomContext,
+        player_cxt: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
         if self.platform_host_id.is_none() {
             self.platform_host_id = Some(player_cxt.id());
             self.platforms
@@ -267,7 +285,11 @@ impl thunders::server::hooks::GameHooks for PongServer {
         }
     }
 
-    fn on_leave(&mut self, player_cxt: &PlayerContext) -> Option<Diff<Self::Delta>> {
+    fn on_leave(
+        &mut self,
+        _room: &mut RoomContext,
+        player_cxt: &PlayerContext,
+    ) -> Option<Diff<Self::Delta>> {
         None
     }
 
@@ -275,8 +297,19 @@ impl thunders::server::hooks::GameHooks for PongServer {
         (false, None)
     }
 
+    // Positions are sent every tick, a lost one is corrected by the next
+    fn delivery(delta: &Self::Delta) -> Delivery {
+        match delta {
+            PongDiff::BallPositionChanged(..) | PongDiff::AwayPositionChanged(..) => {
+                Delivery::Unreliable
+            }
+            _ => Delivery::Reliable,
+        }
+    }
+
     fn on_tick(
         &mut self,
+        room: &mut RoomContext,
         player_cxts: &HashMap<u64, std::sync::Arc<PlayerContext>>,
         actions: Vec<(u64, Self::Action)>,
     ) -> Option<Vec<Diff<Self::Delta>>> {
@@ -360,8 +393,9 @@ impl thunders::server::hooks::GameHooks for PongServer {
             if ball_hit {
                 self.ball.vector.0 *= -1.;
             } else {
+                // Served up or down from the room seed so rooms can be replayed
                 self.ball.vector.0 = 3.5;
-                self.ball.vector.1 = 3.5;
+                self.ball.vector.1 = if room.rng().chance(0.5) { 3.5 } else { -3.5 };
                 self.ball.position.0 = 12.;
                 self.ball.position.1 = 7.;
 
@@ -4,10 +4,10 @@ use thunders::{
     api::schema::json::Json,
     server::{
         ThundersServer, ThundersServerResult,
-        context::PlayerContext,
-        hooks::{Diff, GameHooks},
+        context::{PlayerContext, RoomContext},
+        hooks::{ActionHooks, Diff, GameHooks},
         protocol::ws::WebSocketProtocol,
-        runtime::sync::{Settings, SyncRuntime},
+        runtime::action::{ActionRuntime, Settings},
     },
 };
 
@@ -18,13 +18,7 @@ const IP_ADDRESS: &str = "127.0.0.1";
 #[tokio::main]
 pub async fn main() -> ThundersServerResult {
     ThundersServer::new(WebSocketProtocol::new(IP_ADDRESS, 8080), Json::default())
-        .register::<SyncRuntime<_>, TextEditor>(
-            LOBBY_TYPE,
-            Settings {
-                tick_no_action_millis: 2000,
-                tick_millis: 16,
-            },
-        )
+        .register::<ActionRuntime<_>, TextEditor>(LOBBY_TYPE, Settings::default())
         .run()
         .await
 }
@@ -47,6 +41,7 @@ impl thunders::server::hooks::GameHooks for TextEditor {
     type Delta = TextEditorChange;
     type Action = TextEditorAction;
     type Options = ();
+    type Event = ();
 
     fn build(options: Self::Options) -> Self {
         Self {
@@ -54,44 +49,62 @@ impl thunders::server::hooks::GameHooks for TextEditor {
         }
     }
 
-    fn on_join(&mut self, player_cxt: &PlayerContext) -> Option<Vec<Diff<Self::Delta>>> {
+    // The room never ticks, edits go through ActionHooks::on_action.
+    fn on_tick(
+        &mut self,
+        _room: &mut RoomContext,
+        _players_cxts: &HashMap<u64, Arc<PlayerContext>>,
+        _actions: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _room: &mut RoomContext,
+        player_cxt: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
         Some(vec![Diff::TargetUnique {
             id: player_cxt.id(),
             delta: TextEditorChange::Full(self.content.to_string()),
         }])
     }
 
-    fn on_leave(&mut self, player_cxt: &PlayerContext) -> Option<Diff<Self::Delta>> {
+    fn on_leave(
+        &mut self,
+        _room: &mut RoomContext,
+        player_cxt: &PlayerContext,
+    ) -> Option<Diff<Self::Delta>> {
         None
     }
 
-    fn on_tick(
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+impl ActionHooks for TextEditor {
+    // Every edit is applied in arrival order, none of them is lost.
+    fn on_action(
         &mut self,
+        _room: &mut RoomContext,
         players_cxts: &HashMap<u64, Arc<PlayerContext>>,
-        mut actions: Vec<(u64, Self::Action)>,
+        player_id: u64,
+        action: Self::Action,
     ) -> Option<Vec<Diff<Self::Delta>>> {
-        if actions.is_empty() {
-            None
-        } else {
-            let (p_id, action) = actions.pop().expect("Should have at least one action");
-            match action {
-                TextEditorAction::TextReplace(text) => {
-                    self.content = text;
-                }
+        match action {
+            TextEditorAction::TextReplace(text) => {
+                self.content = text;
             }
-
-            Some(vec![Diff::TargetList {
-                ids: players_cxts
-                    .keys()
-                    .filter(|id| !p_id.eq(*id))
-                    .map(|id| *id)
-                    .collect::<Vec<_>>(),
-                delta: TextEditorChange::Full(self.content.clone()),
-            }])
         }
-    }
 
-    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
-        (false, None)
+        Some(vec![Diff::TargetList {
+            ids: players_cxts
+                .keys()
+                .filter(|id| player_id != **id)
+                .copied()
+                .collect::<Vec<_>>(),
+            delta: TextEditorChange::Full(self.content.clone()),
+        }])
     }
 }
@@ -1,3 +1,11 @@
+pub mod clock;
 pub mod error;
 pub mod message;
 pub mod schema;
+
+#[cfg(any(feature = "quic", feature = "uds"))]
+pub mod framing;
+#[cfg(feature = "memory")]
+pub mod memory;
+#[cfg(feature = "udp")]
+pub mod udp;
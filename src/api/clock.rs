@@ -0,0 +1,169 @@
+use std::{
+    fmt::{self, Debug},
+    pin::Pin,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant, SystemTime},
+};
+
+use tokio::sync::Notify;
+
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// Source of time for runtimes and reply timeouts, swapped by a ManualClock in tests.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Instant;
+
+    fn system_time(&self) -> SystemTime;
+
+    // Completes once the clock moved the duration forward.
+    fn sleep(&self, duration: Duration) -> Sleep;
+
+    // Real time a blocked thread waits before checking the deadline again.
+    fn block_for(&self, deadline: Instant) -> Duration;
+
+    // Called back whenever the clock is moved by hand, while the waker is alive. Blocked threads
+    // check their deadline again then, clocks following real time never call it.
+    fn subscribe(&self, _waker: Weak<dyn Fn() + Send + Sync>) {}
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn block_for(&self, deadline: Instant) -> Duration {
+        deadline.saturating_duration_since(Instant::now())
+    }
+}
+
+// Only moves through advance, clones share the same time.
+#[derive(Clone)]
+pub struct ManualClock {
+    inner: Arc<ManualClockInner>,
+}
+
+struct ManualClockInner {
+    start: Instant,
+    start_system: SystemTime,
+    elapsed: Mutex<Duration>,
+    advanced: Notify,
+    wakers: Mutex<Vec<Weak<dyn Fn() + Send + Sync>>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+
+    // Wall clock time starts at the given one instead of now, e.g. when replaying a recording.
+    pub fn starting_at(start_system: SystemTime) -> Self {
+        Self {
+            inner: Arc::new(ManualClockInner {
+                start: Instant::now(),
+                start_system,
+                elapsed: Mutex::new(Duration::ZERO),
+                advanced: Notify::new(),
+                wakers: Mutex::new(vec![]),
+            }),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self
+            .inner
+            .elapsed
+            .lock()
+            .expect("Lock should never be poisoned") += duration;
+        self.inner.advanced.notify_waiters();
+
+        // Wakers of stopped rooms are dropped along the way
+        self.inner
+            .wakers
+            .lock()
+            .expect("Lock should never be poisoned")
+            .retain(|waker| match waker.upgrade() {
+                Some(waker) => {
+                    waker();
+                    true
+                }
+                None => false,
+            });
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        *self
+            .inner
+            .elapsed
+            .lock()
+            .expect("Lock should never be poisoned")
+    }
+}
+
+impl Debug for ManualClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManualClock")
+            .field("elapsed", &self.elapsed())
+            .finish()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.inner.start + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.inner.start_system + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let clock = self.clone();
+        let deadline = self.now() + duration;
+        Box::pin(async move {
+            loop {
+                // Registered before checking so an advance in between is not missed
+                let advanced = clock.inner.advanced.notified();
+                tokio::pin!(advanced);
+                advanced.as_mut().enable();
+                if clock.now() >= deadline {
+                    break;
+                }
+                advanced.await;
+            }
+        })
+    }
+
+    // Blocked threads wait for advance to call their waker back.
+    fn block_for(&self, deadline: Instant) -> Duration {
+        if self.now() >= deadline {
+            Duration::ZERO
+        } else {
+            Duration::MAX
+        }
+    }
+
+    fn subscribe(&self, waker: Weak<dyn Fn() + Send + Sync>) {
+        self.inner
+            .wakers
+            .lock()
+            .expect("Lock should never be poisoned")
+            .push(waker);
+    }
+}
@@ -0,0 +1,34 @@
+use std::io::{Error, ErrorKind};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// Stream transports delimit messages with a big endian u32 length prefix.
+
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+pub async fn read_frame<R>(reader: &mut R) -> Result<Vec<u8>, Error>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, "Frame too large"));
+    }
+
+    let mut frame = vec![0u8; len];
+    reader.read_exact(&mut frame).await?;
+    Ok(frame)
+}
+
+pub async fn write_frame<W>(writer: &mut W, frame: &[u8]) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin,
+{
+    if frame.len() > MAX_FRAME_SIZE {
+        return Err(Error::new(ErrorKind::InvalidInput, "Frame too large"));
+    }
+
+    writer.write_u32(frame.len() as u32).await?;
+    writer.write_all(frame).await?;
+    writer.flush().await
+}
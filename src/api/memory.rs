@@ -0,0 +1,57 @@
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+// In-process transport, both ends of a connection are plain channels so no socket is involved.
+
+pub struct MemoryConnection {
+    pub(crate) sender: UnboundedSender<Vec<u8>>,
+    pub(crate) receiver: UnboundedReceiver<Vec<u8>>,
+}
+
+impl MemoryConnection {
+    fn pair() -> (Self, Self) {
+        let (server_tx, client_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        let (client_tx, server_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        (
+            Self {
+                sender: server_tx,
+                receiver: server_rx,
+            },
+            Self {
+                sender: client_tx,
+                receiver: client_rx,
+            },
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryConnector {
+    listener_tx: UnboundedSender<MemoryConnection>,
+}
+
+impl MemoryConnector {
+    // Returns the client end, None if the listening side is gone.
+    pub(crate) fn connect(&self) -> Option<MemoryConnection> {
+        let (server_end, client_end) = MemoryConnection::pair();
+        self.listener_tx.send(server_end).ok()?;
+        Some(client_end)
+    }
+}
+
+pub struct MemoryListener {
+    listener_rx: UnboundedReceiver<MemoryConnection>,
+}
+
+impl MemoryListener {
+    pub(crate) async fn accept(&mut self) -> Option<MemoryConnection> {
+        self.listener_rx.recv().await
+    }
+}
+
+pub fn listener() -> (MemoryListener, MemoryConnector) {
+    let (listener_tx, listener_rx) = mpsc::unbounded_channel::<MemoryConnection>();
+    (
+        MemoryListener { listener_rx },
+        MemoryConnector { listener_tx },
+    )
+}
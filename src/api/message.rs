@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 pub enum InputMessage<'a> {
     Connect {
         correlation_id: &'a str,
@@ -8,6 +10,8 @@ pub enum InputMessage<'a> {
         type_: &'a str,
         id: &'a str,
         options: Option<&'a [u8]>,
+        // Replays a room, the server picks one otherwise.
+        seed: Option<u64>,
     },
     Join {
         correlation_id: &'a str,
@@ -18,6 +22,8 @@ pub enum InputMessage<'a> {
         type_: &'a str,
         id: &'a str,
         data: &'a [u8],
+        // Sequence number of the player, acknowledged back in the diffs.
+        seq: Option<u64>,
     },
 }
 
@@ -39,8 +45,37 @@ pub enum OutputMessage<'a> {
         id: &'a str,
         finished: bool,
         data: &'a [u8],
+        // Last action sequence number of the recipient processed by the room.
+        ack: Option<u64>,
+    },
+    Kick {
+        type_: &'a str,
+        id: &'a str,
+        // Free text, owned when it has to be unescaped
+        reason: Cow<'a, str>,
+    },
+    Move {
+        type_: &'a str,
+        id: &'a str,
+        to_type: &'a str,
+        to_id: &'a str,
+    },
+    // The room refused an action of the player, e.g. played out of turn.
+    Reject {
+        type_: &'a str,
+        id: &'a str,
+        reason: Cow<'a, str>,
+        // Sequence number of the refused action, if the client sent one.
+        seq: Option<u64>,
     },
     GenericError {
         description: &'a str,
     },
 }
+
+// Unreliable messages may be dropped or reordered by the transports able to, e.g. UDP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Reliable,
+    Unreliable,
+}
@@ -55,6 +55,7 @@ impl Serialize<Json> for InputMessage<'_> {
                 type_,
                 id,
                 options,
+                seed,
             } => {
                 let mut json_node = serde_json::json!({
                     "method": "create",
@@ -63,6 +64,13 @@ impl Serialize<Json> for InputMessage<'_> {
                     "id": id
                 });
 
+                if let Some(seed) = seed {
+                    json_node
+                        .as_object_mut()
+                        .expect("Should always be a object")
+                        .insert(SEED.to_string(), seed.into());
+                }
+
                 if let Some(options) = options {
                     json_node
                         .as_object_mut()
@@ -86,13 +94,25 @@ impl Serialize<Json> for InputMessage<'_> {
                 "correlation_id": correlation_id,
                 "id": id
             }),
-            Self::Action { type_, id, data } => {
+            Self::Action {
+                type_,
+                id,
+                data,
+                seq,
+            } => {
                 let mut json_node = serde_json::json!({
                     "method": "action",
                     "type": type_,
                     "id": id
                 });
 
+                if let Some(seq) = seq {
+                    json_node
+                        .as_object_mut()
+                        .expect("Should always be a object")
+                        .insert(SEQ.to_string(), seq.into());
+                }
+
                 if !data.is_empty() {
                     json_node
                         .as_object_mut()
@@ -125,7 +145,9 @@ impl<'de> Deserialize<'de, Json> for InputMessage<'de> {
             type Value = InputMessage<'de2>;
 
             fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                f.write_str("flat JSON {method, correlation_id, id, p_id, type, options?, data?}")
+                f.write_str(
+                    "flat JSON {method, correlation_id, id, p_id, type, options?, seed?, data?}",
+                )
             }
 
             fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
@@ -139,6 +161,8 @@ impl<'de> Deserialize<'de, Json> for InputMessage<'de> {
                     PId,
                     Type,
                     Options,
+                    Seed,
+                    Seq,
                     Data,
                     Unknown,
                 }
@@ -158,6 +182,8 @@ impl<'de> Deserialize<'de, Json> for InputMessage<'de> {
                             PLAYER_ID => Field::PId,
                             TYPE => Field::Type,
                             OPTIONS => Field::Options,
+                            SEED => Field::Seed,
+                            SEQ => Field::Seq,
                             DATA => Field::Data,
                             _ => Field::Unknown,
                         })
@@ -170,6 +196,8 @@ impl<'de> Deserialize<'de, Json> for InputMessage<'de> {
                 let mut id: Option<&'de2 str> = None;
                 let mut p_id: Option<u64> = None;
                 let mut options_bytes: Option<&'de2 [u8]> = None;
+                let mut seed: Option<u64> = None;
+                let mut seq: Option<u64> = None;
                 let mut data_bytes: Option<&'de2 [u8]> = None;
 
                 while let Some(f) = map.next_key_seed(FieldSeed)? {
@@ -183,6 +211,8 @@ impl<'de> Deserialize<'de, Json> for InputMessage<'de> {
                             let raw: &RawValue = map.next_value()?;
                             options_bytes = Some(raw.get().as_bytes());
                         }
+                        Field::Seed => seed = Some(map.next_value()?),
+                        Field::Seq => seq = Some(map.next_value()?),
                         Field::Data => {
                             let raw: &RawValue = map.next_value()?;
                             data_bytes = Some(raw.get().as_bytes());
@@ -215,6 +245,7 @@ impl<'de> Deserialize<'de, Json> for InputMessage<'de> {
                             type_: ty,
                             id,
                             options: options_bytes,
+                            seed,
                         })
                     }
                     JOIN => {
@@ -236,6 +267,7 @@ impl<'de> Deserialize<'de, Json> for InputMessage<'de> {
                             type_: ty,
                             id,
                             data,
+                            seq,
                         })
                     }
                     _ => Err(de::Error::custom("unknown method")),
@@ -259,17 +291,26 @@ const JOIN: &str = "join";
 const CREATE: &str = "create";
 const GENERIC_ERROR: &str = "generic_error";
 const DIFF: &str = "diff";
+const KICK: &str = "kick";
+const MOVE: &str = "move";
+const REJECT: &str = "reject";
 const ACTION: &str = "action";
 
 const DATA: &str = "data";
 
 const OPTIONS: &str = "options";
+const SEED: &str = "seed";
+const SEQ: &str = "seq";
+const ACK: &str = "ack";
 const FINISHED: &str = "finished";
 const TYPE: &str = "type";
 const ID: &str = "id";
 
 const PLAYER_ID: &str = "p_id";
 const DESCRIPTION: &str = "description";
+const REASON: &str = "reason";
+const TO_TYPE: &str = "to_type";
+const TO_ID: &str = "to_id";
 const SUCCESS: &str = "success";
 
 impl<'a> Serialize<Json> for OutputMessage<'a> {
@@ -299,6 +340,46 @@ impl<'a> Serialize<Json> for OutputMessage<'a> {
                 CORRELATION_ID: correlation_id,
                 SUCCESS: success
             }),
+            OutputMessage::Kick { type_, id, reason } => serde_json::json!({
+                METHOD: KICK,
+                TYPE: type_,
+                ID: id,
+                REASON: reason
+            }),
+            OutputMessage::Move {
+                type_,
+                id,
+                to_type,
+                to_id,
+            } => serde_json::json!({
+                METHOD: MOVE,
+                TYPE: type_,
+                ID: id,
+                TO_TYPE: to_type,
+                TO_ID: to_id
+            }),
+            OutputMessage::Reject {
+                type_,
+                id,
+                reason,
+                seq,
+            } => {
+                let mut json_node = serde_json::json!({
+                    METHOD: REJECT,
+                    TYPE: type_,
+                    ID: id,
+                    REASON: reason
+                });
+
+                if let Some(seq) = seq {
+                    json_node
+                        .as_object_mut()
+                        .expect("Should always be a object")
+                        .insert(SEQ.to_string(), seq.into());
+                }
+
+                json_node
+            }
             OutputMessage::GenericError { description } => serde_json::json!({
                  METHOD: GENERIC_ERROR,
                  DESCRIPTION : description
@@ -308,6 +389,7 @@ impl<'a> Serialize<Json> for OutputMessage<'a> {
                 id,
                 finished,
                 data,
+                ack,
             } => {
                 let mut json_node = serde_json::json!({
                     METHOD: DIFF,
@@ -316,6 +398,13 @@ impl<'a> Serialize<Json> for OutputMessage<'a> {
                     FINISHED: finished
                 });
 
+                if let Some(ack) = ack {
+                    json_node
+                        .as_object_mut()
+                        .expect("Should always be a object")
+                        .insert(ACK.to_string(), ack.into());
+                }
+
                 if !data.is_empty() {
                     json_node
                         .as_object_mut()
@@ -361,6 +450,11 @@ impl<'de> crate::api::schema::Deserialize<'de, Json> for OutputMessage<'de> {
                     Finished,
                     Data,
                     Description,
+                    Reason,
+                    ToType,
+                    ToId,
+                    Ack,
+                    Seq,
                     Unknown,
                 }
                 struct FieldSeed;
@@ -381,6 +475,11 @@ impl<'de> crate::api::schema::Deserialize<'de, Json> for OutputMessage<'de> {
                             FINISHED => Field::Finished,
                             DATA => Field::Data,
                             DESCRIPTION => Field::Description,
+                            REASON => Field::Reason,
+                            TO_TYPE => Field::ToType,
+                            TO_ID => Field::ToId,
+                            ACK => Field::Ack,
+                            SEQ => Field::Seq,
                             _ => Field::Unknown,
                         })
                     }
@@ -393,6 +492,11 @@ impl<'de> crate::api::schema::Deserialize<'de, Json> for OutputMessage<'de> {
                 let mut success: Option<bool> = None;
                 let mut finished: Option<bool> = None;
                 let mut description: Option<&'de2 str> = None;
+                let mut reason: Option<Cow<'de2, str>> = None;
+                let mut to_type: Option<&'de2 str> = None;
+                let mut to_id: Option<&'de2 str> = None;
+                let mut ack: Option<u64> = None;
+                let mut seq: Option<u64> = None;
                 let mut data_bytes: Option<&'de2 [u8]> = None;
 
                 while let Some(f) = map.next_key_seed(FieldSeed)? {
@@ -404,6 +508,11 @@ impl<'de> crate::api::schema::Deserialize<'de, Json> for OutputMessage<'de> {
                         Field::Success => success = Some(map.next_value()?),
                         Field::Finished => finished = Some(map.next_value()?),
                         Field::Description => description = Some(map.next_value()?),
+                        Field::Reason => reason = Some(map.next_value()?),
+                        Field::ToType => to_type = Some(map.next_value()?),
+                        Field::ToId => to_id = Some(map.next_value()?),
+                        Field::Ack => ack = Some(map.next_value()?),
+                        Field::Seq => seq = Some(map.next_value()?),
                         Field::Data => {
                             let raw: &RawValue = map.next_value()?;
                             data_bytes = Some(raw.get().as_bytes());
@@ -457,6 +566,41 @@ impl<'de> crate::api::schema::Deserialize<'de, Json> for OutputMessage<'de> {
                             id,
                             finished,
                             data,
+                            ack,
+                        })
+                    }
+                    KICK => {
+                        let ty = ty.ok_or_else(|| de::Error::custom("missing `type`"))?;
+                        let id = id.ok_or_else(|| de::Error::custom("missing `id`"))?;
+                        let reason = reason.ok_or_else(|| de::Error::custom("missing `reason`"))?;
+                        Ok(OutputMessage::Kick {
+                            type_: ty,
+                            id,
+                            reason,
+                        })
+                    }
+                    MOVE => {
+                        let ty = ty.ok_or_else(|| de::Error::custom("missing `type`"))?;
+                        let id = id.ok_or_else(|| de::Error::custom("missing `id`"))?;
+                        let to_type =
+                            to_type.ok_or_else(|| de::Error::custom("missing `to_type`"))?;
+                        let to_id = to_id.ok_or_else(|| de::Error::custom("missing `to_id`"))?;
+                        Ok(OutputMessage::Move {
+                            type_: ty,
+                            id,
+                            to_type,
+                            to_id,
+                        })
+                    }
+                    REJECT => {
+                        let ty = ty.ok_or_else(|| de::Error::custom("missing `type`"))?;
+                        let id = id.ok_or_else(|| de::Error::custom("missing `id`"))?;
+                        let reason = reason.ok_or_else(|| de::Error::custom("missing `reason`"))?;
+                        Ok(OutputMessage::Reject {
+                            type_: ty,
+                            id,
+                            reason,
+                            seq,
                         })
                     }
                     GENERIC_ERROR => {
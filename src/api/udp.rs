@@ -0,0 +1,619 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::api::message::Delivery;
+
+// Lightweight reliability layer shared by the UDP client and server protocols.
+//
+// Every data datagram carries its own sequence number plus the latest remote sequence and a 32 bits
+// ack bitfield, so acks piggyback on regular traffic. A flag tells whether the sender received any
+// datagram yet, until then it acknowledges nothing. Reliable messages are resent in new datagrams
+// until one of them gets acknowledged and are delivered in order. Messages bigger than the MTU are
+// split in fragments and rebuilt on the receiving side.
+//
+// Peers must share the settings. Datagrams bigger than the MTU, reliable messages too far ahead of
+// the expected one and unreliable messages beyond the ones being rebuilt are dropped, so a peer
+// cannot make the other one buffer without bounds.
+
+const HANDSHAKE: u8 = 0;
+const HANDSHAKE_ACCEPT: u8 = 1;
+const DATA: u8 = 2;
+const ACK: u8 = 3;
+const DISCONNECT: u8 = 4;
+
+const RELIABLE_FLAG: u8 = 1;
+const ACK_FLAG: u8 = 2;
+
+const HANDSHAKE_HEADER_SIZE: usize = 13;
+const ACK_HEADER_SIZE: usize = 8;
+const DATA_HEADER_SIZE: usize = 16;
+
+const MAX_FRAGMENTS: usize = u8::MAX as usize;
+const MAX_PENDING_RELIABLE: usize = 1024;
+// Senders never have more reliable messages in flight
+const REORDER_WINDOW: u16 = MAX_PENDING_RELIABLE as u16;
+const MAX_PARTIALS: usize = 16;
+
+pub struct UdpSettings {
+    pub protocol_id: u32,
+    pub mtu: usize,
+    pub resend_millis: u64,
+    pub update_millis: u64,
+    pub keep_alive_millis: u64,
+    pub idle_timeout_millis: u64,
+    pub fragment_timeout_millis: u64,
+}
+
+impl Default for UdpSettings {
+    fn default() -> Self {
+        Self {
+            protocol_id: 0x7448_4e44,
+            mtu: 1200,
+            resend_millis: 100,
+            update_millis: 20,
+            keep_alive_millis: 1000,
+            idle_timeout_millis: 10000,
+            fragment_timeout_millis: 3000,
+        }
+    }
+}
+
+pub(crate) enum Packet<'a> {
+    // The nonce tells retries of a handshake from a client restarted on the same address.
+    Handshake {
+        protocol_id: u32,
+        nonce: u64,
+    },
+    HandshakeAccept {
+        protocol_id: u32,
+        nonce: u64,
+    },
+    Data {
+        header: DataHeader,
+        payload: &'a [u8],
+    },
+    Ack {
+        ack: Option<u16>,
+        ack_bits: u32,
+    },
+    Disconnect,
+}
+
+pub(crate) struct DataHeader {
+    seq: u16,
+    ack: Option<u16>,
+    ack_bits: u32,
+    reliable: bool,
+    message_id: u16,
+    group: u16,
+    index: u8,
+    count: u8,
+}
+
+impl<'a> Packet<'a> {
+    pub(crate) fn decode(datagram: &'a [u8]) -> Option<Self> {
+        let (kind, body) = datagram.split_first()?;
+        match *kind {
+            HANDSHAKE if datagram.len() == HANDSHAKE_HEADER_SIZE => Some(Packet::Handshake {
+                protocol_id: read_u32(body, 0),
+                nonce: read_u64(body, 4),
+            }),
+            HANDSHAKE_ACCEPT if datagram.len() == HANDSHAKE_HEADER_SIZE => {
+                Some(Packet::HandshakeAccept {
+                    protocol_id: read_u32(body, 0),
+                    nonce: read_u64(body, 4),
+                })
+            }
+            ACK if datagram.len() == ACK_HEADER_SIZE => Some(Packet::Ack {
+                ack: (body[0] & ACK_FLAG != 0).then(|| read_u16(body, 1)),
+                ack_bits: read_u32(body, 3),
+            }),
+            DATA if datagram.len() >= DATA_HEADER_SIZE => {
+                let header = DataHeader {
+                    seq: read_u16(body, 0),
+                    ack: (body[8] & ACK_FLAG != 0).then(|| read_u16(body, 2)),
+                    ack_bits: read_u32(body, 4),
+                    reliable: body[8] & RELIABLE_FLAG != 0,
+                    message_id: read_u16(body, 9),
+                    group: read_u16(body, 11),
+                    index: body[13],
+                    count: body[14],
+                };
+                if header.count == 0 || header.index >= header.count {
+                    return None;
+                }
+                Some(Packet::Data {
+                    header,
+                    payload: &datagram[DATA_HEADER_SIZE..],
+                })
+            }
+            DISCONNECT if datagram.len() == 1 => Some(Packet::Disconnect),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        match self {
+            Packet::Handshake { protocol_id, nonce } => {
+                let mut buf = Vec::with_capacity(HANDSHAKE_HEADER_SIZE);
+                buf.push(HANDSHAKE);
+                buf.extend_from_slice(&protocol_id.to_be_bytes());
+                buf.extend_from_slice(&nonce.to_be_bytes());
+                buf
+            }
+            Packet::HandshakeAccept { protocol_id, nonce } => {
+                let mut buf = Vec::with_capacity(HANDSHAKE_HEADER_SIZE);
+                buf.push(HANDSHAKE_ACCEPT);
+                buf.extend_from_slice(&protocol_id.to_be_bytes());
+                buf.extend_from_slice(&nonce.to_be_bytes());
+                buf
+            }
+            Packet::Ack { ack, ack_bits } => {
+                let mut buf = Vec::with_capacity(ACK_HEADER_SIZE);
+                buf.push(ACK);
+                buf.push(if ack.is_some() { ACK_FLAG } else { 0 });
+                buf.extend_from_slice(&ack.unwrap_or_default().to_be_bytes());
+                buf.extend_from_slice(&ack_bits.to_be_bytes());
+                buf
+            }
+            Packet::Data { header, payload } => {
+                let mut buf = Vec::with_capacity(DATA_HEADER_SIZE + payload.len());
+                buf.push(DATA);
+                buf.extend_from_slice(&header.seq.to_be_bytes());
+                buf.extend_from_slice(&header.ack.unwrap_or_default().to_be_bytes());
+                buf.extend_from_slice(&header.ack_bits.to_be_bytes());
+                let mut flags = if header.reliable { RELIABLE_FLAG } else { 0 };
+                if header.ack.is_some() {
+                    flags |= ACK_FLAG;
+                }
+                buf.push(flags);
+                buf.extend_from_slice(&header.message_id.to_be_bytes());
+                buf.extend_from_slice(&header.group.to_be_bytes());
+                buf.push(header.index);
+                buf.push(header.count);
+                buf.extend_from_slice(payload);
+                buf
+            }
+            Packet::Disconnect => vec![DISCONNECT],
+        }
+    }
+}
+
+struct PendingMessage {
+    group: u16,
+    index: u8,
+    count: u8,
+    payload: Vec<u8>,
+    last_sent: Instant,
+}
+
+struct PartialMessage {
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+    started_at: Instant,
+}
+
+pub(crate) struct Connection {
+    mtu: usize,
+    resend: Duration,
+    keep_alive: Duration,
+    idle_timeout: Duration,
+    fragment_timeout: Duration,
+
+    local_seq: u16,
+    remote_seq: Option<u16>,
+    ack_bits: u32,
+    ack_pending: bool,
+
+    next_group: u16,
+    next_message_id: u16,
+    pending: HashMap<u16, PendingMessage>,
+    sent_packets: HashMap<u16, u16>,
+
+    expected_message_id: u16,
+    out_of_order: HashMap<u16, (u16, u8, u8, Vec<u8>)>,
+    partials: HashMap<(bool, u16), PartialMessage>,
+
+    last_sent: Instant,
+    last_received: Instant,
+}
+
+impl Connection {
+    pub(crate) fn new(settings: &UdpSettings) -> Self {
+        let now = Instant::now();
+        Self {
+            mtu: settings.mtu,
+            resend: Duration::from_millis(settings.resend_millis),
+            keep_alive: Duration::from_millis(settings.keep_alive_millis),
+            idle_timeout: Duration::from_millis(settings.idle_timeout_millis),
+            fragment_timeout: Duration::from_millis(settings.fragment_timeout_millis),
+            local_seq: 0,
+            remote_seq: None,
+            ack_bits: 0,
+            ack_pending: false,
+            next_group: 0,
+            next_message_id: 0,
+            pending: HashMap::new(),
+            sent_packets: HashMap::new(),
+            expected_message_id: 0,
+            out_of_order: HashMap::new(),
+            partials: HashMap::new(),
+            last_sent: now,
+            last_received: now,
+        }
+    }
+
+    pub(crate) fn send(&mut self, message: &[u8], delivery: Delivery) -> Vec<Vec<u8>> {
+        let max_payload = self.mtu.saturating_sub(DATA_HEADER_SIZE).max(1);
+        let count = message.len().div_ceil(max_payload).max(1);
+        if count > MAX_FRAGMENTS {
+            log::warn!(
+                "Message too big to be fragmented, skipping it. Size: {}",
+                message.len()
+            );
+            return vec![];
+        }
+
+        let reliable = delivery == Delivery::Reliable;
+        if reliable && self.pending.len() + count > MAX_PENDING_RELIABLE {
+            log::warn!("Too many reliable messages waiting for ack, skipping message.");
+            return vec![];
+        }
+
+        let group = self.next_group;
+        self.next_group = self.next_group.wrapping_add(1);
+
+        let now = Instant::now();
+        let mut datagrams = Vec::with_capacity(count);
+        for index in 0..count {
+            let start = index * max_payload;
+            let end = (start + max_payload).min(message.len());
+            let payload = &message[start..end];
+
+            let message_id = if reliable {
+                let message_id = self.next_message_id;
+                self.next_message_id = self.next_message_id.wrapping_add(1);
+                self.pending.insert(
+                    message_id,
+                    PendingMessage {
+                        group,
+                        index: index as u8,
+                        count: count as u8,
+                        payload: payload.to_vec(),
+                        last_sent: now,
+                    },
+                );
+                message_id
+            } else {
+                0
+            };
+
+            datagrams.push(self.encode_data(
+                reliable,
+                message_id,
+                group,
+                index as u8,
+                count as u8,
+                payload,
+            ));
+        }
+
+        self.last_sent = now;
+        datagrams
+    }
+
+    pub(crate) fn receive(&mut self, packet: Packet<'_>) -> Vec<Vec<u8>> {
+        self.last_received = Instant::now();
+        match packet {
+            Packet::Ack { ack, ack_bits } => {
+                self.process_acks(ack, ack_bits);
+                vec![]
+            }
+            Packet::Data { header, payload } => {
+                if DATA_HEADER_SIZE + payload.len() > self.mtu {
+                    log::debug!("Ignored datagram bigger than the MTU");
+                    return vec![];
+                }
+                self.process_acks(header.ack, header.ack_bits);
+                // Left unacknowledged, the peer sends it again once the window moved
+                if header.reliable && !self.is_within_window(header.message_id) {
+                    return vec![];
+                }
+                if !self.track_remote_seq(header.seq) {
+                    return vec![];
+                }
+                self.ack_pending = true;
+
+                if header.reliable {
+                    self.receive_reliable(header, payload)
+                } else {
+                    self.reassemble(false, header.group, header.index, header.count, payload)
+                        .into_iter()
+                        .collect()
+                }
+            }
+            _ => vec![],
+        }
+    }
+
+    // Resends unacknowledged reliable messages and flushes acks when there is no data to piggyback them.
+    pub(crate) fn update(&mut self) -> Vec<Vec<u8>> {
+        let now = Instant::now();
+        let mut datagrams = Vec::new();
+
+        let mut resend_ids = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.last_sent) >= self.resend)
+            .map(|(message_id, _)| *message_id)
+            .collect::<Vec<_>>();
+        resend_ids.sort_by_key(|message_id| message_id.wrapping_sub(self.next_message_id));
+
+        for message_id in resend_ids {
+            let (group, index, count, payload) = {
+                let pending = self
+                    .pending
+                    .get_mut(&message_id)
+                    .expect("Should always exist pending message if listed before");
+                pending.last_sent = now;
+                (
+                    pending.group,
+                    pending.index,
+                    pending.count,
+                    pending.payload.clone(),
+                )
+            };
+            datagrams.push(self.encode_data(true, message_id, group, index, count, &payload));
+        }
+
+        self.sent_packets
+            .retain(|_, message_id| self.pending.contains_key(message_id));
+        self.expire_partials(now);
+
+        if datagrams.is_empty()
+            && (self.ack_pending || now.duration_since(self.last_sent) >= self.keep_alive)
+        {
+            datagrams.push(
+                Packet::Ack {
+                    ack: self.remote_seq,
+                    ack_bits: self.ack_bits,
+                }
+                .encode(),
+            );
+            self.ack_pending = false;
+        }
+
+        if !datagrams.is_empty() {
+            self.last_sent = now;
+        }
+        datagrams
+    }
+
+    // Reliable messages still waiting for an ack.
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        self.last_received.elapsed() >= self.idle_timeout
+    }
+
+    fn encode_data(
+        &mut self,
+        reliable: bool,
+        message_id: u16,
+        group: u16,
+        index: u8,
+        count: u8,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let seq = self.local_seq;
+        self.local_seq = self.local_seq.wrapping_add(1);
+        if reliable {
+            self.sent_packets.insert(seq, message_id);
+        }
+        self.ack_pending = false;
+
+        Packet::Data {
+            header: DataHeader {
+                seq,
+                ack: self.remote_seq,
+                ack_bits: self.ack_bits,
+                reliable,
+                message_id,
+                group,
+                index,
+                count,
+            },
+            payload,
+        }
+        .encode()
+    }
+
+    // Nothing is acknowledged by a peer which received nothing yet.
+    fn process_acks(&mut self, ack: Option<u16>, ack_bits: u32) {
+        let Some(ack) = ack else {
+            return;
+        };
+        self.acknowledge(ack);
+        for bit in 0..32 {
+            if ack_bits & (1 << bit) != 0 {
+                self.acknowledge(ack.wrapping_sub(bit + 1));
+            }
+        }
+    }
+
+    fn acknowledge(&mut self, seq: u16) {
+        if let Some(message_id) = self.sent_packets.remove(&seq) {
+            self.pending.remove(&message_id);
+        }
+    }
+
+    // Returns false when the sequence was already received.
+    fn track_remote_seq(&mut self, seq: u16) -> bool {
+        let Some(remote_seq) = self.remote_seq else {
+            self.remote_seq = Some(seq);
+            self.ack_bits = 0;
+            return true;
+        };
+
+        if sequence_greater_than(seq, remote_seq) {
+            let shift = seq.wrapping_sub(remote_seq) as u32;
+            self.ack_bits = if shift > 32 {
+                0
+            } else if shift == 32 {
+                1 << 31
+            } else {
+                (self.ack_bits << shift) | (1 << (shift - 1))
+            };
+            self.remote_seq = Some(seq);
+            true
+        } else {
+            let distance = remote_seq.wrapping_sub(seq) as u32;
+            if distance == 0 {
+                return false;
+            }
+            if distance <= 32 {
+                let bit = 1 << (distance - 1);
+                if self.ack_bits & bit != 0 {
+                    return false;
+                }
+                self.ack_bits |= bit;
+            }
+            true
+        }
+    }
+
+    // Reliable messages already delivered are acknowledged again, the previous ack may be lost.
+    fn is_within_window(&self, message_id: u16) -> bool {
+        !sequence_greater_than(message_id, self.expected_message_id)
+            || message_id.wrapping_sub(self.expected_message_id) < REORDER_WINDOW
+    }
+
+    // Reliable fragments are delivered in order so at most one reliable message is being
+    // rebuilt, it never expires since its fragments are already acknowledged.
+    fn expire_partials(&mut self, now: Instant) {
+        self.partials.retain(|(reliable, _), partial| {
+            *reliable || now.duration_since(partial.started_at) < self.fragment_timeout
+        });
+    }
+
+    fn receive_reliable(&mut self, header: DataHeader, payload: &[u8]) -> Vec<Vec<u8>> {
+        if header.message_id != self.expected_message_id {
+            if sequence_greater_than(header.message_id, self.expected_message_id) {
+                self.out_of_order.entry(header.message_id).or_insert((
+                    header.group,
+                    header.index,
+                    header.count,
+                    payload.to_vec(),
+                ));
+            }
+            return vec![];
+        }
+
+        let mut messages = Vec::new();
+        let mut next = Some((header.group, header.index, header.count, payload.to_vec()));
+        while let Some((group, index, count, payload)) = next {
+            self.expected_message_id = self.expected_message_id.wrapping_add(1);
+            if let Some(message) = self.reassemble(true, group, index, count, &payload) {
+                messages.push(message);
+            }
+            next = self.out_of_order.remove(&self.expected_message_id);
+        }
+
+        messages
+    }
+
+    fn reassemble(
+        &mut self,
+        reliable: bool,
+        group: u16,
+        index: u8,
+        count: u8,
+        payload: &[u8],
+    ) -> Option<Vec<u8>> {
+        if count == 1 {
+            return Some(payload.to_vec());
+        }
+
+        if !reliable && !self.partials.contains_key(&(reliable, group)) {
+            let now = Instant::now();
+            self.expire_partials(now);
+            // Newer messages matter more, the oldest one being rebuilt is dropped
+            if self
+                .partials
+                .keys()
+                .filter(|(reliable, _)| !reliable)
+                .count()
+                >= MAX_PARTIALS
+                && let Some(oldest) = self
+                    .partials
+                    .iter()
+                    .filter(|((reliable, _), _)| !reliable)
+                    .min_by_key(|(_, partial)| partial.started_at)
+                    .map(|(key, _)| *key)
+            {
+                self.partials.remove(&oldest);
+            }
+        }
+
+        let partial = self
+            .partials
+            .entry((reliable, group))
+            .or_insert_with(|| PartialMessage {
+                fragments: vec![None; count as usize],
+                received: 0,
+                started_at: Instant::now(),
+            });
+
+        if partial.fragments.len() != count as usize {
+            return None;
+        }
+
+        let fragment = &mut partial.fragments[index as usize];
+        if fragment.is_none() {
+            *fragment = Some(payload.to_vec());
+            partial.received += 1;
+        }
+
+        if partial.received < count as usize {
+            return None;
+        }
+
+        self.partials.remove(&(reliable, group)).map(|partial| {
+            partial
+                .fragments
+                .into_iter()
+                .flatten()
+                .flatten()
+                .collect::<Vec<u8>>()
+        })
+    }
+}
+
+fn sequence_greater_than(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(
+        buf[offset..offset + 8]
+            .try_into()
+            .expect("Should always be 8 bytes long"),
+    )
+}
@@ -1,7 +1,10 @@
 use std::{
     collections::HashMap,
     fmt::Debug,
-    sync::{Arc, RwLock},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
     time::Duration,
 };
 
@@ -9,7 +12,7 @@ use tokio::sync::mpsc::UnboundedSender;
 use uuid::Uuid;
 
 use crate::{
-    api::schema::BorrowedSerialize,
+    api::{clock::Clock, schema::BorrowedSerialize},
     client::{
         core::{ActiveGames, GameHooks, InboundAction},
         reply::{Reply, ReplyManager},
@@ -25,8 +28,11 @@ use crate::{
 
 pub mod core;
 pub mod error;
+pub mod lockstep;
+pub mod prediction;
 pub mod protocol;
 mod reply;
+pub mod rollback;
 
 pub type ThundersClientResult = Result<(), ThundersClientError>;
 
@@ -38,6 +44,7 @@ where
     protocol: P,
     _schema: S,
     active_games: Arc<ActiveGames<S>>,
+    clock: Option<Arc<dyn Clock>>,
 }
 
 impl<P, S> ThundersClientBuilder<P, S>
@@ -52,9 +59,16 @@ where
             active_games: Arc::new(ActiveGames::<S> {
                 current: HashMap::default(),
             }),
+            clock: None,
         }
     }
 
+    // Measures reply timeouts, e.g. a ManualClock to expire them from tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
     pub fn register(mut self, type_: &'static str) -> Self {
         Arc::get_mut(&mut self.active_games)
             .expect("Should always have unique owner")
@@ -68,12 +82,16 @@ where
         for<'a> OutputMessage<'a>: Deserialize<'a, S>,
     {
         let p_handle = self.protocol.run(Arc::clone(&self.active_games)).await?;
+        if let Some(clock) = self.clock {
+            p_handle.reply_manager.set_clock(clock);
+        }
 
         Ok(ThundersClient::<S> {
             action_tx: p_handle.action_tx,
             event_rx: p_handle.event_rx,
             reply_manager: p_handle.reply_manager,
             active_games: self.active_games,
+            next_seq: AtomicU64::new(1),
         })
     }
 }
@@ -83,6 +101,8 @@ pub struct ThundersClient<S: Schema> {
     event_rx: async_channel::Receiver<InternalEvent>,
     reply_manager: Arc<ReplyManager<ThundersClientError>>,
     pub active_games: Arc<ActiveGames<S>>,
+    // Sequence number of the next action, shared by every room of the player
+    next_seq: AtomicU64,
 }
 
 impl<S> Debug for ThundersClient<S>
@@ -94,8 +114,32 @@ where
     }
 }
 
+#[derive(Debug)]
 pub enum InternalEvent {
-    RoomUpdated { type_: String, id: String },
+    RoomUpdated {
+        type_: String,
+        id: String,
+    },
+    // The room was finished locally
+    Kicked {
+        type_: String,
+        id: String,
+        reason: String,
+    },
+    // Same type rooms keep the game state under the new id, otherwise it is finished and the new
+    // one must be created through ActiveGames::create.
+    Moved {
+        type_: String,
+        id: String,
+        to_type: String,
+        to_id: String,
+    },
+    // Already applied locally, the room state is corrected by its next diffs.
+    ActionRejected {
+        type_: String,
+        id: String,
+        reason: String,
+    },
 }
 
 impl<S: Schema + 'static> ThundersClient<S> {
@@ -110,7 +154,7 @@ impl<S: Schema + 'static> ThundersClient<S> {
             id: player_id,
         });
 
-        if let Ok(reply) = reply.await {
+        if let Ok(reply) = self.reply_manager.wait(reply, expires_in).await {
             match reply {
                 Reply::Timeout => Err(ThundersClientError::NoResponse),
                 Reply::Err(err) => Err(err),
@@ -128,6 +172,39 @@ impl<S: Schema + 'static> ThundersClient<S> {
         options: G::Options,
         expires_in: Duration,
     ) -> ThundersClientResult
+    where
+        G::Change: for<'a> Deserialize<'a, S>,
+        G::Options: Serialize<S>,
+    {
+        self.create_room::<G>(type_, id, options, None, expires_in)
+            .await
+    }
+
+    // Same as create but the room draws from the given seed, used to replay a recorded room.
+    pub async fn create_seeded<G: GameHooks + Send + Sync + 'static>(
+        &self,
+        type_: &'static str,
+        id: &str,
+        options: G::Options,
+        seed: u64,
+        expires_in: Duration,
+    ) -> ThundersClientResult
+    where
+        G::Change: for<'a> Deserialize<'a, S>,
+        G::Options: Serialize<S>,
+    {
+        self.create_room::<G>(type_, id, options, Some(seed), expires_in)
+            .await
+    }
+
+    async fn create_room<G: GameHooks + Send + Sync + 'static>(
+        &self,
+        type_: &'static str,
+        id: &str,
+        options: G::Options,
+        seed: Option<u64>,
+        expires_in: Duration,
+    ) -> ThundersClientResult
     where
         G::Change: for<'a> Deserialize<'a, S>,
         G::Options: Serialize<S>,
@@ -152,10 +229,11 @@ impl<S: Schema + 'static> ThundersClient<S> {
             type_,
             id,
             options,
+            seed,
         });
 
         let mut should_rollback = true;
-        let result = if let Ok(reply) = reply.await {
+        let result = if let Ok(reply) = self.reply_manager.wait(reply, expires_in).await {
             match reply {
                 Reply::Timeout => Err(ThundersClientError::NoResponse),
                 Reply::Err(err) => Err(err),
@@ -199,7 +277,7 @@ impl<S: Schema + 'static> ThundersClient<S> {
             id,
         });
         let mut should_rollback = true;
-        let result = if let Ok(reply) = reply.await {
+        let result = if let Ok(reply) = self.reply_manager.wait(reply, expires_in).await {
             match reply {
                 Reply::Timeout => Err(ThundersClientError::NoResponse),
                 Reply::Err(err) => Err(err),
@@ -229,13 +307,15 @@ impl<S: Schema + 'static> ThundersClient<S> {
     where
         G::Action: BorrowedSerialize<S>,
     {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
         self.try_send(InputMessage::Action {
             type_: type_,
             id: id,
             data: action.serialize().as_slice(),
+            seq: Some(seq),
         });
 
-        self.active_games.action::<G>(type_, id, action)
+        self.active_games.action::<G>(type_, id, action, seq)
     }
 
     pub async fn consume_event(&self) -> Result<InternalEvent, ThundersClientError> {
@@ -246,9 +326,13 @@ impl<S: Schema + 'static> ThundersClient<S> {
     }
 
     fn try_send(&self, message: InputMessage) {
-        self.action_tx
+        if self
+            .action_tx
             .send(InboundAction::Raw(message.serialize()))
-            .expect("Should always be consumer active if client handle alive");
+            .is_err()
+        {
+            log::warn!("Connection closed, skipping message.");
+        }
     }
 }
 
@@ -257,8 +341,7 @@ where
     S: Schema,
 {
     fn drop(&mut self) {
-        self.action_tx
-            .send(InboundAction::Stop)
-            .expect("Should always be consumer active if client handle alive");
+        // The server may have closed the connection already
+        let _ = self.action_tx.send(InboundAction::Stop);
     }
 }
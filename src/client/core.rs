@@ -20,15 +20,35 @@ pub trait GameHooks {
     fn on_change(&mut self, change: Self::Change);
     fn on_action(&mut self, action: Self::Action);
     fn on_finish(self);
+
+    // Called instead of on_action with the sequence number the action is sent with.
+    fn on_sequenced_action(&mut self, seq: u64, action: Self::Action) {
+        let _ = seq;
+        self.on_action(action);
+    }
+
+    // Called instead of on_change with the last sequence number of the player processed by the
+    // room, see prediction::Predicted.
+    fn on_acknowledged_change(&mut self, ack: Option<u64>, change: Self::Change) {
+        let _ = ack;
+        self.on_change(change);
+    }
+
+    // The room refused the action sent with this sequence number, it never gets acknowledged.
+    fn on_rejected_action(&mut self, seq: u64) {
+        let _ = seq;
+    }
 }
 
 pub trait GenericGameHooks<S>
 where
     S: Schema,
 {
-    fn on_change(&mut self, change: &[u8]) -> Result<(), ThundersClientError>;
+    fn on_change(&mut self, change: &[u8], ack: Option<u64>) -> Result<(), ThundersClientError>;
+
+    fn on_action(&mut self, action: Box<dyn Any>, seq: u64) -> Result<(), ThundersClientError>;
 
-    fn on_action(&mut self, action: Box<dyn Any>) -> Result<(), ThundersClientError>;
+    fn on_rejected_action(&mut self, seq: u64);
 
     fn as_any(&self) -> &dyn Any;
 
@@ -42,24 +62,28 @@ where
     T::Action: 'static,
     T::Change: for<'a> Deserialize<'a, S> + Debug,
 {
-    fn on_change(&mut self, change: &[u8]) -> Result<(), ThundersClientError> {
+    fn on_change(&mut self, change: &[u8], ack: Option<u64>) -> Result<(), ThundersClientError> {
         if let Ok(change) = <T::Change as Deserialize<S>>::deserialize(change) {
-            self.on_change(change);
+            self.on_acknowledged_change(ack, change);
             Ok(())
         } else {
             Err(ThundersClientError::UnknownMessage)
         }
     }
 
-    fn on_action(&mut self, action: Box<dyn Any>) -> Result<(), ThundersClientError> {
+    fn on_action(&mut self, action: Box<dyn Any>, seq: u64) -> Result<(), ThundersClientError> {
         if let Ok(action) = action.downcast::<T::Action>() {
-            self.on_action(*action);
+            self.on_sequenced_action(seq, *action);
             Ok(())
         } else {
             Err(ThundersClientError::IncompatibleAction)
         }
     }
 
+    fn on_rejected_action(&mut self, seq: u64) {
+        GameHooks::on_rejected_action(self, seq);
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -100,7 +124,13 @@ where
 }
 
 impl<S: Schema> ActiveGames<S> {
-    pub fn route_message(&self, type_: &str, id: &str, message: &[u8]) -> ThundersClientResult {
+    pub fn route_message(
+        &self,
+        type_: &str,
+        id: &str,
+        message: &[u8],
+        ack: Option<u64>,
+    ) -> ThundersClientResult {
         self.current
             .get(type_)
             .ok_or(ThundersClientError::RoomTypeNotFound)?
@@ -109,7 +139,20 @@ impl<S: Schema> ActiveGames<S> {
             .get_mut(id)
             .ok_or(ThundersClientError::RoomNotFound)?
             .as_mut()
-            .on_change(message)
+            .on_change(message, ack)
+    }
+
+    pub fn route_rejection(&self, type_: &str, id: &str, seq: u64) -> ThundersClientResult {
+        self.current
+            .get(type_)
+            .ok_or(ThundersClientError::RoomTypeNotFound)?
+            .write()
+            .expect("Should always get write lock successfully")
+            .get_mut(id)
+            .ok_or(ThundersClientError::RoomNotFound)?
+            .as_mut()
+            .on_rejected_action(seq);
+        Ok(())
     }
 
     pub fn get_as<G: GameHooks + Send + Sync + 'static>(
@@ -159,6 +202,7 @@ impl<S: Schema> ActiveGames<S> {
         type_: &'static str,
         id: &str,
         action: G::Action,
+        seq: u64,
     ) -> ThundersClientResult {
         self.current
             .get(type_)
@@ -167,7 +211,20 @@ impl<S: Schema> ActiveGames<S> {
             .expect("Should always get write lock successfully")
             .get_mut(id)
             .ok_or(ThundersClientError::RoomNotFound)?
-            .on_action(Box::new(action) as Box<dyn Any>)
+            .on_action(Box::new(action) as Box<dyn Any>, seq)
+    }
+
+    pub fn relocate(&self, type_: &str, id: &str, to_id: String) -> ThundersClientResult {
+        let mut games = self
+            .current
+            .get(type_)
+            .ok_or(ThundersClientError::RoomTypeNotFound)?
+            .write()
+            .expect("Should always get write lock successfully");
+        let game = games.remove(id).ok_or(ThundersClientError::RoomNotFound)?;
+        games.insert(to_id, game);
+
+        Ok(())
     }
 
     pub fn remove(
@@ -9,4 +9,5 @@ pub enum ThundersClientError {
     GameJoinFailure,
     GameCreationFailure,
     EventListenerNotConfigured,
+    InvalidTlsConfig,
 }
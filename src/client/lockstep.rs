@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+// Client side of the lockstep runtime, kept by the client GameHooks. Frames broadcast by the room
+// are handed to the local simulation in order, and only once received.
+#[derive(Debug)]
+pub struct LockstepClient<I> {
+    // Next frame to simulate
+    frame: u64,
+    input_delay: u64,
+    frames: BTreeMap<u64, Vec<(u64, I)>>,
+}
+
+impl<I> LockstepClient<I> {
+    // Must match the input delay of the room settings.
+    pub fn new(input_delay: u64) -> Self {
+        Self {
+            frame: 0,
+            input_delay,
+            frames: BTreeMap::new(),
+        }
+    }
+
+    // Next frame to simulate.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // Frame a local input taken now is meant for, sent along with it.
+    pub fn input_frame(&self) -> u64 {
+        self.frame + self.input_delay
+    }
+
+    // Called from on_change with the frames broadcast by the room.
+    pub fn receive(&mut self, frame: u64, inputs: Vec<(u64, I)>) {
+        if frame < self.frame {
+            log::warn!("Frame {frame} received after being simulated");
+            return;
+        }
+        self.frames.insert(frame, inputs);
+    }
+
+    // Inputs of the next frame, None until the room broadcast it. The first frames are within the
+    // input delay so they never have inputs.
+    pub fn next_frame(&mut self) -> Option<(u64, Vec<(u64, I)>)> {
+        let inputs = if self.frame < self.input_delay {
+            vec![]
+        } else {
+            self.frames.remove(&self.frame)?
+        };
+        let frame = self.frame;
+        self.frame += 1;
+        Some((frame, inputs))
+    }
+
+    // Frames ready to be simulated, more than one means the simulation is behind.
+    pub fn ready(&self) -> usize {
+        let mut frame = self.frame;
+        let mut count = 0;
+        while frame < self.input_delay || self.frames.contains_key(&frame) {
+            frame += 1;
+            count += 1;
+        }
+        count
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+use crate::client::core::GameHooks;
+
+// Client-side prediction over any GameHooks. Local actions are applied right away to the predicted
+// game, then applied again on top of the authoritative one until a diff of the room acknowledges
+// them or the room rejects them. Rooms must be run by a runtime acknowledging actions, the diffs
+// alone are applied otherwise. Register Predicted<G> instead of G and keep calling action with G::Action.
+pub struct Predicted<G>
+where
+    G: GameHooks,
+{
+    // Game built from the diffs only
+    authoritative: G,
+    predicted: G,
+    // Actions sent but not acknowledged yet, in sequence order
+    pending: VecDeque<(u64, G::Action)>,
+}
+
+impl<G> Predicted<G>
+where
+    G: GameHooks + Clone,
+    G::Action: Clone,
+{
+    pub fn predicted(&self) -> &G {
+        &self.predicted
+    }
+
+    pub fn authoritative(&self) -> &G {
+        &self.authoritative
+    }
+
+    // Actions not acknowledged by the room yet.
+    pub fn pending(&self) -> impl Iterator<Item = &G::Action> {
+        self.pending.iter().map(|(_, action)| action)
+    }
+
+    fn reconcile(&mut self) {
+        self.predicted = self.authoritative.clone();
+        for (_, action) in self.pending.iter() {
+            self.predicted.on_action(action.clone());
+        }
+    }
+}
+
+impl<G> GameHooks for Predicted<G>
+where
+    G: GameHooks + Clone,
+    G::Action: Clone,
+{
+    type Change = G::Change;
+    type Action = G::Action;
+    type Options = G::Options;
+
+    fn build(options: &Self::Options) -> Self {
+        let authoritative = G::build(options);
+        Self {
+            predicted: authoritative.clone(),
+            authoritative,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.on_acknowledged_change(None, change);
+    }
+
+    fn on_action(&mut self, action: Self::Action) {
+        self.predicted.on_action(action);
+    }
+
+    fn on_finish(self) {
+        self.predicted.on_finish();
+    }
+
+    fn on_sequenced_action(&mut self, seq: u64, action: Self::Action) {
+        self.predicted.on_action(action.clone());
+        self.pending.push_back((seq, action));
+    }
+
+    fn on_rejected_action(&mut self, seq: u64) {
+        self.pending.retain(|(pending_seq, _)| *pending_seq != seq);
+        self.reconcile();
+    }
+
+    fn on_acknowledged_change(&mut self, ack: Option<u64>, change: Self::Change) {
+        self.authoritative.on_change(change);
+        if let Some(ack) = ack {
+            while self.pending.front().is_some_and(|(seq, _)| *seq <= ack) {
+                self.pending.pop_front();
+            }
+        }
+        self.reconcile();
+    }
+}
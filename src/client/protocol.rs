@@ -12,6 +12,16 @@ use crate::{
 };
 use tokio::sync::mpsc::UnboundedSender;
 
+#[cfg(feature = "memory")]
+pub mod memory;
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "udp")]
+pub mod udp;
+#[cfg(all(unix, feature = "uds"))]
+pub mod uds;
 #[cfg(feature = "ws")]
 pub mod ws;
 
@@ -30,3 +40,129 @@ pub trait ClientProtocol {
         S: Schema + 'static,
         for<'a> OutputMessage<'a>: Deserialize<'a, S>;
 }
+
+pub(crate) async fn process_message<S>(
+    raw_message: Vec<u8>,
+    active_games: &ActiveGames<S>,
+    reply_manager: &ReplyManager<ThundersClientError>,
+    event_tx: &async_channel::Sender<InternalEvent>,
+) where
+    S: Schema + 'static,
+    for<'a> OutputMessage<'a>: Deserialize<'a, S>,
+{
+    let raw_message_ref = raw_message.as_slice();
+    if let Ok(output) = <OutputMessage as Deserialize<S>>::deserialize(raw_message_ref) {
+        match output {
+            OutputMessage::Connect {
+                correlation_id,
+                success,
+            } => {
+                if success {
+                    reply_manager.ok_no_result(correlation_id);
+                } else {
+                    reply_manager.error(correlation_id, ThundersClientError::ConnectionFailure);
+                }
+            }
+            OutputMessage::Join {
+                correlation_id,
+                success,
+            } => {
+                if success {
+                    reply_manager.ok_no_result(correlation_id);
+                } else {
+                    reply_manager.error(correlation_id, ThundersClientError::GameJoinFailure);
+                }
+            }
+            OutputMessage::Create {
+                correlation_id,
+                success,
+            } => {
+                if success {
+                    reply_manager.ok_no_result(correlation_id);
+                } else {
+                    reply_manager.error(correlation_id, ThundersClientError::GameCreationFailure);
+                }
+            }
+            OutputMessage::Diff {
+                type_,
+                id,
+                finished,
+                data,
+                ack,
+            } => {
+                if finished {
+                    if let Ok(room) = active_games.remove(type_, id) {
+                        room.on_finished();
+                    }
+                } else if let Err(err) = active_games.route_message(type_, id, data, ack) {
+                    log::error!("Message routing failed. Type: {type_}, Id: {id}, Error: {err:?}");
+                } else {
+                    let _ = event_tx
+                        .send(InternalEvent::RoomUpdated {
+                            type_: type_.to_string(),
+                            id: id.to_string(),
+                        })
+                        .await;
+                }
+            }
+            OutputMessage::Kick { type_, id, reason } => {
+                if let Ok(room) = active_games.remove(type_, id) {
+                    room.on_finished();
+                }
+                let _ = event_tx
+                    .send(InternalEvent::Kicked {
+                        type_: type_.to_string(),
+                        id: id.to_string(),
+                        reason: reason.into_owned(),
+                    })
+                    .await;
+            }
+            OutputMessage::Move {
+                type_,
+                id,
+                to_type,
+                to_id,
+            } => {
+                if type_ == to_type {
+                    let _ = active_games.relocate(type_, id, to_id.to_string());
+                } else if let Ok(room) = active_games.remove(type_, id) {
+                    room.on_finished();
+                }
+                let _ = event_tx
+                    .send(InternalEvent::Moved {
+                        type_: type_.to_string(),
+                        id: id.to_string(),
+                        to_type: to_type.to_string(),
+                        to_id: to_id.to_string(),
+                    })
+                    .await;
+            }
+            OutputMessage::Reject {
+                type_,
+                id,
+                reason,
+                seq,
+            } => {
+                if let Some(seq) = seq
+                    && let Err(err) = active_games.route_rejection(type_, id, seq)
+                {
+                    log::error!(
+                        "Rejection routing failed. Type: {type_}, Id: {id}, Error: {err:?}"
+                    );
+                }
+                let _ = event_tx
+                    .send(InternalEvent::ActionRejected {
+                        type_: type_.to_string(),
+                        id: id.to_string(),
+                        reason: reason.into_owned(),
+                    })
+                    .await;
+            }
+            OutputMessage::GenericError { description } => {
+                log::error!("Received error message. Description: {description}");
+            }
+        }
+    } else {
+        log::error!("Ignored message due to serialization failure");
+    }
+}
@@ -0,0 +1,94 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::client::{
+    InternalEvent,
+    core::{ActiveGames, InboundAction},
+    reply::ReplyManager,
+};
+use crate::{
+    api::{
+        memory::MemoryConnector,
+        message::OutputMessage,
+        schema::{Deserialize, Schema},
+    },
+    client::{
+        error::ThundersClientError,
+        protocol::{ClientProtocol, ClientProtocolHandle, process_message},
+    },
+};
+
+pub struct MemoryClientProtocol {
+    connector: MemoryConnector,
+}
+
+impl MemoryClientProtocol {
+    pub fn new(connector: MemoryConnector) -> Self {
+        Self { connector }
+    }
+}
+
+impl ClientProtocol for MemoryClientProtocol {
+    async fn run<S>(
+        self,
+        active_games: Arc<ActiveGames<S>>,
+    ) -> Result<ClientProtocolHandle, ThundersClientError>
+    where
+        S: Schema + 'static,
+        for<'a> OutputMessage<'a>: Deserialize<'a, S>,
+    {
+        let mut connection = self
+            .connector
+            .connect()
+            .ok_or(ThundersClientError::ConnectionFailure)?;
+
+        let (action_tx, mut action_rx) = tokio::sync::mpsc::unbounded_channel::<InboundAction>();
+        let (event_tx, event_rx) = async_channel::unbounded::<InternalEvent>();
+
+        let reply_manager = Arc::new(ReplyManager::new());
+
+        tokio::spawn({
+            let reply_manager = Arc::clone(&reply_manager);
+            async move {
+                let mut vacuum_interval = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    tokio::select! {
+                        _ = vacuum_interval.tick() => {
+                            reply_manager.vacuum();
+                        },
+                        Some(inbound_action) = action_rx.recv() => {
+                            match inbound_action {
+                                InboundAction::Raw(data) => {
+                                    if connection.sender.send(data).is_err() {
+                                        break;
+                                    }
+                                }
+                                InboundAction::Stop => {
+                                    break;
+                                }
+                            }
+                        },
+                        raw_message = connection.receiver.recv() => match raw_message {
+                            Some(raw_message) => {
+                                process_message(
+                                    raw_message,
+                                    active_games.as_ref(),
+                                    reply_manager.as_ref(),
+                                    &event_tx,
+                                )
+                                .await;
+                            }
+                            // Connection closed by the server
+                            None => break,
+                        },
+                    }
+                }
+            }
+        });
+
+        Ok(ClientProtocolHandle {
+            action_tx,
+            event_rx,
+            reply_manager,
+        })
+    }
+}
@@ -0,0 +1,173 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use quinn::{
+    ClientConfig, Endpoint,
+    rustls::{RootCertStore, pki_types::CertificateDer},
+};
+
+use crate::client::{
+    InternalEvent,
+    core::{ActiveGames, InboundAction},
+    reply::ReplyManager,
+};
+use crate::{
+    api::{
+        framing::{read_frame, write_frame},
+        message::OutputMessage,
+        schema::{Deserialize, Schema},
+    },
+    client::{
+        error::ThundersClientError,
+        protocol::{ClientProtocol, ClientProtocolHandle, process_message},
+    },
+};
+
+pub struct QuicClientProtocol {
+    pub addr: String,
+    pub port: u16,
+    pub server_name: String,
+    pub root_certificates: Vec<CertificateDer<'static>>,
+}
+
+impl QuicClientProtocol {
+    pub fn new(addr: impl Into<String>, port: u16) -> Self {
+        Self {
+            addr: addr.into(),
+            port,
+            server_name: "localhost".to_string(),
+            root_certificates: vec![],
+        }
+    }
+
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = server_name.into();
+        self
+    }
+
+    pub fn with_trusted_certificate(mut self, certificate: CertificateDer<'static>) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+}
+
+impl ClientProtocol for QuicClientProtocol {
+    async fn run<S>(
+        self,
+        active_games: Arc<ActiveGames<S>>,
+    ) -> Result<ClientProtocolHandle, ThundersClientError>
+    where
+        S: Schema + 'static,
+        for<'a> OutputMessage<'a>: Deserialize<'a, S>,
+    {
+        let addr = tokio::net::lookup_host((self.addr.as_str(), self.port))
+            .await
+            .map_err(|_| ThundersClientError::ConnectionFailure)?
+            .next()
+            .ok_or(ThundersClientError::ConnectionFailure)?;
+
+        let mut roots = RootCertStore::empty();
+        for certificate in self.root_certificates {
+            roots
+                .add(certificate)
+                .map_err(|_| ThundersClientError::ConnectionFailure)?;
+        }
+        let client_config = ClientConfig::with_root_certificates(Arc::new(roots))
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+
+        let bind_addr: SocketAddr = if addr.is_ipv6() {
+            "[::]:0".parse().expect("Should always be a valid address")
+        } else {
+            "0.0.0.0:0"
+                .parse()
+                .expect("Should always be a valid address")
+        };
+        let mut endpoint =
+            Endpoint::client(bind_addr).map_err(|_| ThundersClientError::ConnectionFailure)?;
+        endpoint.set_default_client_config(client_config);
+
+        let connection = endpoint
+            .connect(addr, self.server_name.as_str())
+            .map_err(|_| ThundersClientError::ConnectionFailure)?
+            .await
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+        let (mut send, mut recv) = connection
+            .open_bi()
+            .await
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+
+        let (action_tx, mut action_rx) = tokio::sync::mpsc::unbounded_channel::<InboundAction>();
+        let (event_tx, event_rx) = async_channel::unbounded::<InternalEvent>();
+
+        let reply_manager = Arc::new(ReplyManager::new());
+
+        // Reading a frame is not cancel safe, so the control stream and the datagrams get their own readers
+        tokio::spawn({
+            let active_games = Arc::clone(&active_games);
+            let reply_manager = Arc::clone(&reply_manager);
+            let event_tx = event_tx.clone();
+            async move {
+                while let Ok(raw_message) = read_frame(&mut recv).await {
+                    process_message(
+                        raw_message,
+                        active_games.as_ref(),
+                        reply_manager.as_ref(),
+                        &event_tx,
+                    )
+                    .await;
+                }
+            }
+        });
+
+        tokio::spawn({
+            let connection = connection.clone();
+            let reply_manager = Arc::clone(&reply_manager);
+            async move {
+                while let Ok(datagram) = connection.read_datagram().await {
+                    process_message(
+                        datagram.into(),
+                        active_games.as_ref(),
+                        reply_manager.as_ref(),
+                        &event_tx,
+                    )
+                    .await;
+                }
+            }
+        });
+
+        tokio::spawn({
+            let reply_manager = Arc::clone(&reply_manager);
+            async move {
+                let mut vacuum_interval = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    tokio::select! {
+                        _ = vacuum_interval.tick() => {
+                            reply_manager.vacuum();
+                        },
+                        Some(inbound_action) = action_rx.recv() => {
+                            match inbound_action {
+                                InboundAction::Raw(data) => {
+                                    if write_frame(&mut send, &data).await.is_err() {
+                                        break;
+                                    }
+                                }
+                                InboundAction::Stop => {
+                                    break;
+                                }
+                            }
+                        },
+                    }
+                }
+
+                let _ = send.finish();
+                connection.close(0u32.into(), b"");
+                endpoint.wait_idle().await;
+            }
+        });
+
+        Ok(ClientProtocolHandle {
+            action_tx,
+            event_rx,
+            reply_manager,
+        })
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use rustls::{
+    ClientConfig, RootCertStore,
+    crypto::ring,
+    pki_types::{CertificateDer, ServerName, pem::PemObject},
+};
+use tokio_rustls::TlsConnector;
+
+use crate::client::error::ThundersClientError;
+
+pub struct ClientTlsConfig {
+    roots: RootCertStore,
+    server_name: Option<String>,
+}
+
+impl ClientTlsConfig {
+    // Trusts the Mozilla root certificates bundled by webpki-roots.
+    pub fn new() -> Self {
+        Self {
+            roots: RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            },
+            server_name: None,
+        }
+    }
+
+    // Trusted on top of the bundled roots, e.g. a self-signed certificate.
+    pub fn with_root_certificate(
+        mut self,
+        certificate: CertificateDer<'static>,
+    ) -> Result<Self, ThundersClientError> {
+        self.roots
+            .add(certificate)
+            .map_err(|_| ThundersClientError::InvalidTlsConfig)?;
+        Ok(self)
+    }
+
+    pub fn with_root_pem(self, pem: &[u8]) -> Result<Self, ThundersClientError> {
+        CertificateDer::pem_slice_iter(pem).try_fold(self, |config, certificate| {
+            config.with_root_certificate(
+                certificate.map_err(|_| ThundersClientError::InvalidTlsConfig)?,
+            )
+        })
+    }
+
+    // Name checked against the server certificate, defaults to the address being connected to.
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    pub(crate) fn into_connector(
+        self,
+        default_server_name: &str,
+    ) -> Result<(TlsConnector, ServerName<'static>), ThundersClientError> {
+        let server_name = ServerName::try_from(
+            self.server_name
+                .unwrap_or_else(|| default_server_name.to_string()),
+        )
+        .map_err(|_| ThundersClientError::InvalidTlsConfig)?;
+
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|_| ThundersClientError::InvalidTlsConfig)?
+            .with_root_certificates(self.roots)
+            .with_no_client_auth();
+        Ok((TlsConnector::from(Arc::new(config)), server_name))
+    }
+}
+
+impl Default for ClientTlsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,182 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::net::UdpSocket;
+use uuid::Uuid;
+
+use crate::client::{
+    InternalEvent,
+    core::{ActiveGames, InboundAction},
+    reply::ReplyManager,
+};
+use crate::{
+    api::{
+        message::{Delivery, OutputMessage},
+        schema::{Deserialize, Schema},
+        udp::{Connection, Packet, UdpSettings},
+    },
+    client::{
+        error::ThundersClientError,
+        protocol::{ClientProtocol, ClientProtocolHandle, process_message},
+    },
+};
+
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+pub struct UdpClientProtocol {
+    pub addr: String,
+    pub port: u16,
+    pub settings: UdpSettings,
+}
+
+impl UdpClientProtocol {
+    pub fn new(addr: impl Into<String>, port: u16) -> Self {
+        Self {
+            addr: addr.into(),
+            port,
+            settings: UdpSettings::default(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: UdpSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    async fn handshake(&self, socket: &UdpSocket) -> Result<(), ThundersClientError> {
+        let nonce = Uuid::new_v4().as_u64_pair().0;
+        let handshake = Packet::Handshake {
+            protocol_id: self.settings.protocol_id,
+            nonce,
+        }
+        .encode();
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        let mut retry_interval =
+            tokio::time::interval(Duration::from_millis(self.settings.resend_millis));
+
+        let accepted = async {
+            loop {
+                tokio::select! {
+                    _ = retry_interval.tick() => {
+                        socket
+                            .send(&handshake)
+                            .await
+                            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+                    }
+                    result = socket.recv(&mut buf) => {
+                        let Ok(len) = result else {
+                            continue;
+                        };
+                        if let Some(Packet::HandshakeAccept { protocol_id, nonce: accepted_nonce }) = Packet::decode(&buf[..len])
+                            && protocol_id == self.settings.protocol_id
+                            && accepted_nonce == nonce
+                        {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        };
+
+        tokio::time::timeout(
+            Duration::from_millis(self.settings.idle_timeout_millis),
+            accepted,
+        )
+        .await
+        .map_err(|_| ThundersClientError::ConnectionFailure)?
+    }
+}
+
+impl ClientProtocol for UdpClientProtocol {
+    async fn run<S>(
+        self,
+        active_games: Arc<ActiveGames<S>>,
+    ) -> Result<ClientProtocolHandle, ThundersClientError>
+    where
+        S: Schema + 'static,
+        for<'a> OutputMessage<'a>: Deserialize<'a, S>,
+    {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+        socket
+            .connect(format!("{}:{}", self.addr, self.port).as_str())
+            .await
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+        self.handshake(&socket).await?;
+
+        let (action_tx, mut action_rx) = tokio::sync::mpsc::unbounded_channel::<InboundAction>();
+        let (event_tx, event_rx) = async_channel::unbounded::<InternalEvent>();
+
+        let reply_manager = Arc::new(ReplyManager::new());
+        let mut connection = Connection::new(&self.settings);
+
+        tokio::spawn({
+            let reply_manager = Arc::clone(&reply_manager);
+            async move {
+                let mut vacuum_interval = tokio::time::interval(Duration::from_secs(60));
+                let mut update_interval =
+                    tokio::time::interval(Duration::from_millis(self.settings.update_millis));
+                let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+                loop {
+                    tokio::select! {
+                        _ = vacuum_interval.tick() => {
+                            reply_manager.vacuum();
+                        },
+                        _ = update_interval.tick() => {
+                            if connection.is_idle() {
+                                log::warn!("UDP connection timed out");
+                                break;
+                            }
+                            for datagram in connection.update() {
+                                let _ = socket.send(&datagram).await;
+                            }
+                        },
+                        Some(inbound_action) = action_rx.recv() => {
+                            match inbound_action {
+                                InboundAction::Raw(data) => {
+                                    for datagram in connection.send(&data, Delivery::Reliable) {
+                                        let _ = socket.send(&datagram).await;
+                                    }
+                                }
+                                InboundAction::Stop => {
+                                    let _ = socket.send(&Packet::Disconnect.encode()).await;
+                                    break;
+                                }
+                            }
+                        },
+                        result = socket.recv(&mut buf) => {
+                            let Ok(len) = result else {
+                                continue;
+                            };
+                            match Packet::decode(&buf[..len]) {
+                                Some(Packet::Disconnect) => {
+                                    break;
+                                }
+                                Some(packet) => {
+                                    for raw_message in connection.receive(packet) {
+                                        process_message(
+                                            raw_message,
+                                            active_games.as_ref(),
+                                            reply_manager.as_ref(),
+                                            &event_tx,
+                                        )
+                                        .await;
+                                    }
+                                }
+                                None => {
+                                    log::error!("Ignored datagram due to decoding failure");
+                                }
+                            }
+                        },
+                    }
+                }
+            }
+        });
+
+        Ok(ClientProtocolHandle {
+            action_tx,
+            event_rx,
+            reply_manager,
+        })
+    }
+}
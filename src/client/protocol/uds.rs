@@ -0,0 +1,107 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use tokio::net::UnixStream;
+
+use crate::client::{
+    InternalEvent,
+    core::{ActiveGames, InboundAction},
+    reply::ReplyManager,
+};
+use crate::{
+    api::{
+        framing::{read_frame, write_frame},
+        message::OutputMessage,
+        schema::{Deserialize, Schema},
+    },
+    client::{
+        error::ThundersClientError,
+        protocol::{ClientProtocol, ClientProtocolHandle, process_message},
+    },
+};
+
+pub struct UnixSocketClientProtocol {
+    pub path: PathBuf,
+}
+
+impl UnixSocketClientProtocol {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl ClientProtocol for UnixSocketClientProtocol {
+    async fn run<S>(
+        self,
+        active_games: Arc<ActiveGames<S>>,
+    ) -> Result<ClientProtocolHandle, ThundersClientError>
+    where
+        S: Schema + 'static,
+        for<'a> OutputMessage<'a>: Deserialize<'a, S>,
+    {
+        let stream = UnixStream::connect(&self.path)
+            .await
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+        let (mut read, mut write) = stream.into_split();
+
+        let (action_tx, mut action_rx) = tokio::sync::mpsc::unbounded_channel::<InboundAction>();
+        let (event_tx, event_rx) = async_channel::unbounded::<InternalEvent>();
+
+        let reply_manager = Arc::new(ReplyManager::new());
+
+        // Reading a frame is not cancel safe, so it gets its own task instead of a select branch
+        let reader = tokio::spawn({
+            let reply_manager = Arc::clone(&reply_manager);
+            async move {
+                while let Ok(raw_message) = read_frame(&mut read).await {
+                    process_message(
+                        raw_message,
+                        active_games.as_ref(),
+                        reply_manager.as_ref(),
+                        &event_tx,
+                    )
+                    .await;
+                }
+            }
+        });
+
+        tokio::spawn({
+            let reply_manager = Arc::clone(&reply_manager);
+            async move {
+                let mut vacuum_interval = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    tokio::select! {
+                        _ = vacuum_interval.tick() => {
+                            reply_manager.vacuum();
+                        },
+                        Some(inbound_action) = action_rx.recv() => {
+                            match inbound_action {
+                                InboundAction::Raw(data) => {
+                                    if write_frame(&mut write, &data).await.is_err() {
+                                        break;
+                                    }
+                                }
+                                InboundAction::Stop => {
+                                    break;
+                                }
+                            }
+                        },
+                    }
+                }
+
+                reader.abort();
+            }
+        });
+
+        Ok(ClientProtocolHandle {
+            action_tx,
+            event_rx,
+            reply_manager,
+        })
+    }
+}
@@ -1,11 +1,25 @@
 use futures::{SinkExt, StreamExt};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    time::Instant,
+};
 use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{Bytes, Message, client::IntoClientRequest},
+    WebSocketStream, client_async_with_config,
+    tungstenite::{
+        Bytes, Message,
+        client::IntoClientRequest,
+        handshake::client::Request,
+        http::{HeaderName, HeaderValue, header::SEC_WEBSOCKET_PROTOCOL},
+        protocol::WebSocketConfig,
+    },
 };
 
+#[cfg(feature = "tls")]
+use crate::client::protocol::tls::ClientTlsConfig;
+
 use crate::client::{
     InternalEvent,
     core::{ActiveGames, InboundAction},
@@ -18,23 +32,161 @@ use crate::{
     },
     client::{
         error::ThundersClientError,
-        protocol::{ClientProtocol, ClientProtocolHandle},
+        protocol::{ClientProtocol, ClientProtocolHandle, process_message},
     },
 };
 
 pub struct WebSocketClientProtocol {
-    pub addr: String,
-    pub port: u16,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub subprotocols: Vec<String>,
+    pub connect_timeout: Option<Duration>,
+    pub max_message_size: Option<usize>,
+    pub heartbeat_interval: Duration,
+    pub idle_timeout: Duration,
+    #[cfg(feature = "tls")]
+    pub tls_config: Option<ClientTlsConfig>,
 }
 
 impl WebSocketClientProtocol {
     pub fn new(addr: impl Into<String>, port: u16) -> Self {
+        Self::from_url(format!("ws://{}:{}", addr.into(), port))
+    }
+
+    // Full endpoint including path and query, e.g. "wss://example.com/game?token=abc"
+    pub fn from_url(url: impl Into<String>) -> Self {
         Self {
-            addr: addr.into(),
-            port,
+            url: url.into(),
+            headers: vec![],
+            subprotocols: vec![],
+            connect_timeout: None,
+            max_message_size: None,
+            heartbeat_interval: Duration::from_secs(15),
+            idle_timeout: Duration::from_secs(45),
+            #[cfg(feature = "tls")]
+            tls_config: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    // Offered in preference order, the server picks one of them.
+    pub fn with_subprotocol(mut self, subprotocol: impl Into<String>) -> Self {
+        self.subprotocols.push(subprotocol.into());
+        self
+    }
+
+    // Covers TCP connect, TLS and websocket handshakes.
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = Some(max_message_size);
+        self
+    }
+
+    // Pings are sent every interval, the connection is dropped when the server stays silent for
+    // longer than the idle timeout.
+    pub fn with_heartbeat(mut self, interval: Duration, idle_timeout: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    // Connects through wss:// instead of ws://
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls_config: ClientTlsConfig) -> Self {
+        if let Some(rest) = self.url.strip_prefix("ws://") {
+            self.url = format!("wss://{rest}");
+        }
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    fn request(&self) -> Result<Request, ThundersClientError> {
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+
+        for (name, value) in self.headers.iter() {
+            request.headers_mut().append(
+                HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| ThundersClientError::ConnectionFailure)?,
+                HeaderValue::from_str(value).map_err(|_| ThundersClientError::ConnectionFailure)?,
+            );
         }
+
+        if !self.subprotocols.is_empty() {
+            request.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_str(self.subprotocols.join(", ").as_str())
+                    .map_err(|_| ThundersClientError::ConnectionFailure)?,
+            );
+        }
+
+        Ok(request)
+    }
+
+    async fn connect(self) -> Result<WebSocketStream<Box<dyn WsStream>>, ThundersClientError> {
+        let request = self.request()?;
+        let is_secure = request.uri().scheme_str() == Some("wss");
+        let host = request
+            .uri()
+            .host()
+            .ok_or(ThundersClientError::ConnectionFailure)?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let port = request
+            .uri()
+            .port_u16()
+            .unwrap_or(if is_secure { 443 } else { 80 });
+        let config = WebSocketConfig::default().max_message_size(self.max_message_size);
+
+        let stream = TcpStream::connect((host.as_str(), port))
+            .await
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+
+        let stream: Box<dyn WsStream> = if is_secure {
+            #[cfg(feature = "tls")]
+            {
+                let (connector, server_name) = self
+                    .tls_config
+                    .unwrap_or_default()
+                    .into_connector(host.as_str())?;
+                Box::new(
+                    connector
+                        .connect(server_name, stream)
+                        .await
+                        .map_err(|_| ThundersClientError::ConnectionFailure)?,
+                )
+            }
+            #[cfg(not(feature = "tls"))]
+            {
+                return Err(ThundersClientError::ConnectionFailure);
+            }
+        } else {
+            Box::new(stream)
+        };
+
+        let (stream, _) = client_async_with_config(request, stream, Some(config))
+            .await
+            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+        Ok(stream)
     }
 }
+
+trait WsStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T> WsStream for T where T: AsyncRead + AsyncWrite + Unpin + Send {}
+
 impl ClientProtocol for WebSocketClientProtocol {
     async fn run<S>(
         self,
@@ -44,106 +196,113 @@ impl ClientProtocol for WebSocketClientProtocol {
         S: Schema + 'static,
         for<'a> OutputMessage<'a>: Deserialize<'a, S>,
     {
-        let request = format!("ws://{}:{}", self.addr, self.port)
-            .into_client_request()
-            .map_err(|_| ThundersClientError::ConnectionFailure)?;
-        let (stream, _) = connect_async(request)
-            .await
-            .map_err(|_| ThundersClientError::ConnectionFailure)?;
+        let heartbeat_interval = self.heartbeat_interval;
+        let idle_timeout = self.idle_timeout;
+        let stream = match self.connect_timeout {
+            Some(connect_timeout) => tokio::time::timeout(connect_timeout, self.connect())
+                .await
+                .map_err(|_| ThundersClientError::ConnectionFailure)??,
+            None => self.connect().await?,
+        };
 
-        let (action_tx, mut action_rx) = tokio::sync::mpsc::unbounded_channel::<InboundAction>();
-        let (event_tx, mut event_rx) = async_channel::unbounded::<InternalEvent>();
-        let (mut ws_writer, mut ws_receiver) = stream.split();
-
-        let reply_manager = Arc::new(ReplyManager::new());
-
-        tokio::spawn({
-            let reply_manager = Arc::clone(&reply_manager);
-            async move {
-                let mut vacuum_interval = tokio::time::interval(std::time::Duration::from_secs(60));
-                loop {
-                    tokio::select! {
-                         _ = vacuum_interval.tick() => {
-                            reply_manager.vacuum();
-                         },
-                         Some(inbound_action) = action_rx.recv() => {
-                             match inbound_action {
-                                 InboundAction::Raw(data) => {
-                            if let Err(_) = ws_writer
-                                 .send(Message::Binary(data.into()))
-                                 .await {
-                                     break;
-                                }
-                             }
-                                 InboundAction::Stop => {
-                                     break;
-                                 }
-                             }
-                         },
-                         Some(Ok(message)) = ws_receiver.next() => {
-                            let raw_message = message_into_bytes(message);
-                            let raw_message_ref = raw_message.as_slice();
-                            if let Ok(output) = <OutputMessage as Deserialize<S>>::deserialize(raw_message_ref) {
-                                           match output {
-                                                OutputMessage::Connect{correlation_id, success} => {
-                                                    if success {
-                                                        reply_manager.ok_no_result(correlation_id );
-                                                    } else {
-                                                        reply_manager.error(correlation_id, ThundersClientError::ConnectionFailure);
-                                                    }
-                                               },
-                                               OutputMessage::Join{correlation_id, success} => {
-                                                    if success {
-                                                        reply_manager.ok_no_result(correlation_id );
-                                                    } else {
-                                                        reply_manager.error(correlation_id, ThundersClientError::GameJoinFailure);
-                                                    }
-                                              },
-                                               OutputMessage::Create{correlation_id, success} => {
-                                                    if success {
-                                                        reply_manager.ok_no_result(correlation_id);
-                                                    } else {
-                                                        reply_manager.error(correlation_id, ThundersClientError::GameCreationFailure);
-                                                    }
-                                               }
-                                              OutputMessage::Diff{type_, id, finished, data} => {
-                                                 if finished {
-                                                      if let Ok(room) = active_games.remove(type_.as_ref(), id.as_ref()) {
-                                                          room.on_finished();
-                                                      }
-                                                } else if let Err(err) = active_games.route_message(type_.as_ref(), id.as_ref(), data) {
-                                                     log::error!("Message routing failed. Type: {}, Id: {}, Error: {err:?}", type_, id);
-                                                } else {
-                                                    let _ = event_tx.send(InternalEvent::RoomUpdated { type_: type_.to_string(), id: id.to_string() } ).await;
-                                               }
-
-                                           }
-                                               OutputMessage::GenericError {description} => {
-                                                   log::error!("Received error message. Description: {description}");
-                                               }
-                                        }
-                            } else {
-                                log::error!("Ignored message due to serialization failure");
+        Ok(start(
+            stream,
+            active_games,
+            heartbeat_interval,
+            idle_timeout,
+        ))
+    }
+}
+
+fn start<S, T>(
+    stream: WebSocketStream<T>,
+    active_games: Arc<ActiveGames<S>>,
+    heartbeat_interval: Duration,
+    idle_timeout: Duration,
+) -> ClientProtocolHandle
+where
+    S: Schema + 'static,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    for<'a> OutputMessage<'a>: Deserialize<'a, S>,
+{
+    let (action_tx, mut action_rx) = tokio::sync::mpsc::unbounded_channel::<InboundAction>();
+    let (event_tx, mut event_rx) = async_channel::unbounded::<InternalEvent>();
+    let (mut ws_writer, mut ws_receiver) = stream.split();
+
+    let reply_manager = Arc::new(ReplyManager::new());
+
+    tokio::spawn({
+        let reply_manager = Arc::clone(&reply_manager);
+        async move {
+            let mut vacuum_interval = tokio::time::interval(std::time::Duration::from_secs(60));
+            let mut heartbeat_interval =
+                tokio::time::interval_at(Instant::now() + heartbeat_interval, heartbeat_interval);
+            let mut last_received = Instant::now();
+            loop {
+                tokio::select! {
+                     _ = vacuum_interval.tick() => {
+                        reply_manager.vacuum();
+                     },
+                     _ = heartbeat_interval.tick() => {
+                        if last_received.elapsed() > idle_timeout {
+                            log::warn!("Websocket connection timed out");
+                            break;
+                        }
+                        if ws_writer.send(Message::Ping(Bytes::new())).await.is_err() {
+                            break;
+                        }
+                     },
+                     Some(inbound_action) = action_rx.recv() => {
+                         match inbound_action {
+                             InboundAction::Raw(data) => {
+                        if let Err(_) = ws_writer
+                             .send(Message::Binary(data.into()))
+                             .await {
+                                 break;
+                            }
+                         }
+                             InboundAction::Stop => {
+                                 let _ = ws_writer.send(Message::Close(None)).await;
+                                 break;
                              }
-                         },
-                    }
+                         }
+                     },
+                     message = ws_receiver.next() => {
+                        last_received = Instant::now();
+                        // Pings are answered by tungstenite itself
+                        match message {
+                            Some(Ok(Message::Binary(bytes))) => {
+                                process_message(
+                                    bytes.into(),
+                                    active_games.as_ref(),
+                                    reply_manager.as_ref(),
+                                    &event_tx,
+                                )
+                                .await;
+                            }
+                            Some(Ok(Message::Text(bytes))) => {
+                                process_message(
+                                    Bytes::from(bytes).into(),
+                                    active_games.as_ref(),
+                                    reply_manager.as_ref(),
+                                    &event_tx,
+                                )
+                                .await;
+                            }
+                            Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                                break;
+                            }
+                            Some(Ok(_)) => {}
+                        }
+                     },
                 }
             }
-        });
-
-        Ok(ClientProtocolHandle {
-            action_tx,
-            event_rx,
-            reply_manager,
-        })
-    }
-}
-fn message_into_bytes(message: Message) -> Vec<u8> {
-    match message {
-        Message::Binary(bytes) => bytes.into(),
-        Message::Text(bytes) => Bytes::from(bytes).into(),
-        _ => {
-            vec![]
         }
+    });
+
+    ClientProtocolHandle {
+        action_tx,
+        event_rx,
+        reply_manager,
     }
 }
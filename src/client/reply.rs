@@ -1,10 +1,12 @@
 use std::{
     collections::{BinaryHeap, HashMap},
-    sync::{Mutex, RwLock},
+    sync::{Arc, Mutex, RwLock},
     time::{Duration, Instant},
 };
 
-use tokio::sync::oneshot::{self, Receiver, Sender};
+use tokio::sync::oneshot::{self, Receiver, Sender, error::RecvError};
+
+use crate::api::clock::{Clock, SystemClock};
 
 pub enum Reply<R, E> {
     Ok(R),
@@ -34,6 +36,7 @@ impl PartialOrd for RegisteredTimeout {
 pub struct ReplyManager<E, R = ()> {
     replies_registry: Mutex<HashMap<String, Sender<Reply<R, E>>>>,
     registered_timeouts: RwLock<BinaryHeap<RegisteredTimeout>>,
+    clock: RwLock<Arc<dyn Clock>>,
 }
 
 impl<R, E> ReplyManager<E, R> {
@@ -41,9 +44,27 @@ impl<R, E> ReplyManager<E, R> {
         Self {
             replies_registry: Mutex::new(HashMap::new()),
             registered_timeouts: RwLock::new(BinaryHeap::new()),
+            clock: RwLock::new(Arc::new(SystemClock)),
         }
     }
 
+    // Set by the client builder once the protocol created the manager.
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        *self
+            .clock
+            .write()
+            .expect("Should write lock always be acquirable") = clock;
+    }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        Arc::clone(
+            &self
+                .clock
+                .read()
+                .expect("Should read lock always be acquired"),
+        )
+    }
+
     pub fn register(&self, id: &str, expires_in: Duration) -> Receiver<Reply<R, E>> {
         let (tx, rx) = oneshot::channel::<Reply<R, E>>();
 
@@ -57,13 +78,27 @@ impl<R, E> ReplyManager<E, R> {
             .expect("Should write lock always be acquirable")
             .push(RegisteredTimeout {
                 id: id.to_string(),
-                expires_at: Instant::now()
+                expires_at: self
+                    .clock()
+                    .now()
                     .checked_add(expires_in)
                     .expect("Should expires never overflow internal structure"),
             });
         rx
     }
 
+    // Times out as soon as the clock reaches the expiration instead of waiting for a vacuum.
+    pub async fn wait(
+        &self,
+        reply: Receiver<Reply<R, E>>,
+        expires_in: Duration,
+    ) -> Result<Reply<R, E>, RecvError> {
+        tokio::select! {
+            reply = reply => reply,
+            _ = self.clock().sleep(expires_in) => Ok(Reply::Timeout),
+        }
+    }
+
     pub fn ok(&self, id: &str, result: R) {
         if let Some(pending_reply) = self
             .replies_registry
@@ -98,7 +133,7 @@ impl<R, E> ReplyManager<E, R> {
     }
 
     pub fn vacuum(&self) {
-        let now = Instant::now();
+        let now = self.clock().now();
         loop {
             if let Some(registered_timeout) = self
                 .registered_timeouts
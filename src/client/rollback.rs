@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+// Game simulated by the client and rolled back when confirmed inputs differ from the predicted
+// ones. Every frame must be deterministic given its inputs.
+pub trait RollbackHooks {
+    type State;
+    type Input: Clone + PartialEq;
+
+    fn save_state(&self) -> Self::State;
+
+    fn load_state(&mut self, state: &Self::State);
+
+    // Simulates one frame, inputs are ordered by player id.
+    fn advance(&mut self, frame: u64, inputs: &[(u64, Self::Input)]);
+
+    // Input assumed for a player whose input of the frame did not arrive yet, the last one by
+    // default. None leaves the player out of the frame.
+    fn predict(&self, player_id: u64, last: Option<&Self::Input>) -> Option<Self::Input> {
+        let _ = player_id;
+        last.cloned()
+    }
+}
+
+// Rollback session kept by the client GameHooks, see server::runtime::relay. Local inputs are
+// simulated right away with predicted remote ones, and frames are simulated again once the
+// confirmed inputs arrive different from the predictions. Players must agree on the first frame,
+// e.g. through the server frame relayed along with the inputs.
+pub struct Rollback<G>
+where
+    G: RollbackHooks,
+{
+    game: G,
+    player_id: u64,
+    window: u64,
+    // Next frame to simulate
+    frame: u64,
+    // First frame not having the inputs of every player yet
+    confirmed: u64,
+    // First frame of every player, they are left out of the frames before
+    players: BTreeMap<u64, u64>,
+    inputs: BTreeMap<u64, BTreeMap<u64, G::Input>>,
+    // Inputs each simulated frame ran with, predictions included
+    simulated: BTreeMap<u64, Vec<(u64, G::Input)>>,
+    // Saved before simulating the frame
+    states: BTreeMap<u64, G::State>,
+}
+
+impl<G> Rollback<G>
+where
+    G: RollbackHooks,
+{
+    // Players of the match, the local one included. The window bounds how many frames the local
+    // simulation may run ahead of the confirmed ones.
+    pub fn new(
+        game: G,
+        player_id: u64,
+        players: impl IntoIterator<Item = u64>,
+        window: u64,
+    ) -> Self {
+        let mut players = players
+            .into_iter()
+            .map(|player_id| (player_id, 0))
+            .collect::<BTreeMap<_, _>>();
+        players.insert(player_id, 0);
+        Self {
+            game,
+            player_id,
+            window: window.max(1),
+            frame: 0,
+            confirmed: 0,
+            players,
+            inputs: BTreeMap::new(),
+            simulated: BTreeMap::new(),
+            states: BTreeMap::new(),
+        }
+    }
+
+    // Player joining the match, frames are waited for its inputs from the given one.
+    pub fn add_player(&mut self, player_id: u64, frame: u64) {
+        let frame = frame.max(self.confirmed);
+        self.players.insert(player_id, frame);
+        if frame < self.frame {
+            self.rollback(frame);
+        }
+    }
+
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    // Next frame to simulate, the local input given to advance is meant for it.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // Frames before it have the inputs of every known player.
+    pub fn confirmed_frame(&self) -> u64 {
+        self.confirmed
+    }
+
+    // False while the simulation waits for remote inputs, local inputs must not be sent then.
+    pub fn can_advance(&self) -> bool {
+        self.frame < self.confirmed + self.window
+    }
+
+    // Simulates the next frame with the local input, None if the window is full.
+    pub fn advance(&mut self, input: G::Input) -> Option<u64> {
+        if !self.can_advance() {
+            return None;
+        }
+        let frame = self.frame;
+        self.inputs
+            .entry(frame)
+            .or_default()
+            .insert(self.player_id, input);
+        self.simulate(frame);
+        self.frame += 1;
+        self.update_confirmed();
+        Some(frame)
+    }
+
+    // Called from on_change with the inputs relayed by the room. Returns the frame the simulation
+    // rolled back to, if any.
+    pub fn receive(&mut self, player_id: u64, frame: u64, input: G::Input) -> Option<u64> {
+        if player_id == self.player_id {
+            return None;
+        }
+        if !self.players.contains_key(&player_id) {
+            log::warn!("Input of player {player_id} ignored, not part of the match");
+            return None;
+        }
+        if frame < self.confirmed {
+            log::error!("Input of player {player_id} for frame {frame} is out of the window");
+            return None;
+        }
+
+        let mispredicted = frame < self.frame
+            && self.simulated.get(&frame).is_none_or(|inputs| {
+                !inputs
+                    .iter()
+                    .any(|(id, predicted)| *id == player_id && *predicted == input)
+            });
+        self.inputs
+            .entry(frame)
+            .or_default()
+            .insert(player_id, input);
+
+        if mispredicted {
+            self.rollback(frame);
+        }
+        self.update_confirmed();
+        mispredicted.then_some(frame)
+    }
+
+    fn rollback(&mut self, frame: u64) {
+        let state = self
+            .states
+            .get(&frame)
+            .expect("Should always keep the states within the window");
+        self.game.load_state(state);
+        for frame in frame..self.frame {
+            self.simulate(frame);
+        }
+    }
+
+    fn simulate(&mut self, frame: u64) {
+        self.states.insert(frame, self.game.save_state());
+        let known = self.inputs.get(&frame);
+        let previous = frame
+            .checked_sub(1)
+            .and_then(|previous| self.simulated.get(&previous));
+        let inputs = self
+            .players
+            .iter()
+            .filter(|(_, first_frame)| **first_frame <= frame)
+            .filter_map(|(player_id, _)| {
+                let input = match known.and_then(|inputs| inputs.get(player_id)) {
+                    Some(input) => Some(input.clone()),
+                    None => {
+                        let last = previous.and_then(|inputs| {
+                            inputs
+                                .iter()
+                                .find(|(id, _)| id == player_id)
+                                .map(|(_, input)| input)
+                        });
+                        self.game.predict(*player_id, last)
+                    }
+                };
+                input.map(|input| (*player_id, input))
+            })
+            .collect::<Vec<_>>();
+        self.game.advance(frame, inputs.as_slice());
+        self.simulated.insert(frame, inputs);
+    }
+
+    // Confirmed frames are never rolled back, only the last one is kept to predict from.
+    fn update_confirmed(&mut self) {
+        while self.confirmed < self.frame
+            && self.inputs.get(&self.confirmed).is_some_and(|inputs| {
+                self.players
+                    .iter()
+                    .filter(|(_, first_frame)| **first_frame <= self.confirmed)
+                    .all(|(player_id, _)| inputs.contains_key(player_id))
+            })
+        {
+            self.confirmed += 1;
+        }
+
+        let keep_from = self.confirmed.saturating_sub(1);
+        self.states = self.states.split_off(&self.confirmed);
+        self.simulated = self.simulated.split_off(&keep_from);
+        self.inputs = self.inputs.split_off(&keep_from);
+    }
+}
@@ -5,3 +5,6 @@ pub mod client;
 
 #[cfg(feature = "server")]
 pub mod server;
+
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use crate::{
     api::{
@@ -7,6 +7,7 @@ use crate::{
     },
     server::{
         error::ThundersServerError,
+        handle::ServerHandle,
         hooks::GameHooks,
         protocol::{NetworkProtocol, SessionManager},
         runtime::{GameRuntime, GameRuntimeAnyHandle, GameRuntimeHandle},
@@ -15,8 +16,10 @@ use crate::{
 
 pub mod context;
 pub mod error;
+pub mod handle;
 pub mod hooks;
 pub mod protocol;
+pub mod replay;
 pub mod runtime;
 
 pub struct ThundersServer<N, S>
@@ -28,6 +31,7 @@ where
     _schema: S,
     handlers: HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>,
     session_manager: Arc<SessionManager>,
+    shutdown_timeout: Duration,
 }
 
 impl<N, S> ThundersServer<N, S>
@@ -41,9 +45,28 @@ where
             _schema: schema,
             handlers: Default::default(),
             session_manager: Arc::new(SessionManager::default()),
+            shutdown_timeout: Duration::from_secs(5),
         }
     }
 
+    // Serves the same rooms through one more protocol, e.g. websocket for browsers and UDP for
+    // native clients.
+    pub fn with_protocol<M: NetworkProtocol>(self, protocol: M) -> ThundersServer<(N, M), S> {
+        ThundersServer {
+            protocol: (self.protocol, protocol),
+            _schema: self._schema,
+            handlers: self.handlers,
+            session_manager: self.session_manager,
+            shutdown_timeout: self.shutdown_timeout,
+        }
+    }
+
+    // Maximum wait for connections to drain on shutdown, after rooms were finished.
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
     pub fn register<R: GameRuntime<H, S> + 'static, H: GameHooks>(
         mut self,
         type_: &'static str,
@@ -69,10 +92,98 @@ where
     where
         for<'a> InputMessage<'a>: Deserialize<'a, S>,
     {
-        let handlers: &'static HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>> =
-            Box::leak(Box::new(self.handlers));
+        self.run_until(std::future::pending()).await
+    }
+
+    // Once shutdown completes new connections are rejected, rooms are force-finished and their
+    // runtimes joined, then connections are closed and drained within the shutdown timeout.
+    pub async fn run_until(self, shutdown: impl Future<Output = ()>) -> ThundersServerResult
+    where
+        for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    {
+        let (_, server) = self.run_with_handle(shutdown);
+        server.await
+    }
+
+    // Same as run_until, also handing out control over the server. Consumes the server so no room
+    // type can be registered afterwards.
+    pub fn run_with_handle<F>(
+        self,
+        shutdown: F,
+    ) -> (
+        ServerHandle<S>,
+        impl Future<Output = ThundersServerResult> + use<N, S, F>,
+    )
+    where
+        F: Future<Output = ()>,
+        for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    {
+        let handlers = Arc::new(self.handlers);
+        let session_manager = self.session_manager;
+        session_manager.attach(&handlers);
+
+        let handle = ServerHandle::new(Arc::clone(&session_manager), Arc::clone(&handlers));
+        let server = Self::serve(
+            self.protocol,
+            handlers,
+            session_manager,
+            self.shutdown_timeout,
+            shutdown,
+        );
+        (handle, server)
+    }
+
+    async fn serve(
+        protocol: N,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+        session_manager: Arc<SessionManager>,
+        shutdown_timeout: Duration,
+        shutdown: impl Future<Output = ()>,
+    ) -> ThundersServerResult
+    where
+        for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    {
+        let protocol = protocol.run::<S>(Arc::clone(&session_manager), Arc::clone(&handlers));
+        tokio::pin!(protocol);
+
+        tokio::select! {
+            result = &mut protocol => return result,
+            _ = shutdown => {}
+        }
+
+        log::info!("Shutting down server");
+        session_manager.close();
+
+        let finishing = Arc::clone(&handlers);
+        if tokio::task::spawn_blocking(move || {
+            for handler in finishing.values() {
+                handler.shutdown();
+            }
+        })
+        .await
+        .is_err()
+        {
+            log::error!("Failed to finish rooms on shutdown");
+        }
+
+        // Connections keep being served until every player disconnected
+        session_manager.disconnect_all();
+        let drained = async {
+            let mut drain_interval = tokio::time::interval(Duration::from_millis(10));
+            while !session_manager.is_drained() {
+                drain_interval.tick().await;
+            }
+        };
+        tokio::select! {
+            _ = &mut protocol => {}
+            result = tokio::time::timeout(shutdown_timeout, drained) => {
+                if result.is_err() {
+                    log::warn!("Shutdown timed out with connections still open");
+                }
+            }
+        }
 
-        self.protocol.run::<S>(self.session_manager, handlers).await
+        Ok(())
     }
 }
 
@@ -1,20 +1,345 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    ops::Range,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::api::clock::Clock;
 
 #[derive(Debug)]
 pub struct PlayerContext {
     id: u64,
     attrs: HashMap<String, String>,
+    metadata: ConnectionMetadata,
 }
 
 impl PlayerContext {
     pub fn new(id: u64) -> Self {
+        Self::with_metadata(id, ConnectionMetadata::default(), HashMap::default())
+    }
+
+    pub fn with_metadata(
+        id: u64,
+        metadata: ConnectionMetadata,
+        attrs: HashMap<String, String>,
+    ) -> Self {
         Self {
             id,
-            attrs: HashMap::default(),
+            attrs,
+            metadata,
         }
     }
 
     pub fn id(&self) -> u64 {
         self.id
     }
+
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs.get(key).map(String::as_str)
+    }
+
+    pub fn attrs(&self) -> &HashMap<String, String> {
+        &self.attrs
+    }
+
+    pub fn metadata(&self) -> &ConnectionMetadata {
+        &self.metadata
+    }
+}
+
+// What the transport knows about the connection, path, query and headers are only filled by HTTP based ones.
+#[derive(Debug, Default, Clone)]
+pub struct ConnectionMetadata {
+    pub remote_addr: Option<SocketAddr>,
+    pub path: Option<String>,
+    pub query: Option<String>,
+    pub headers: HashMap<String, String>,
+}
+
+impl ConnectionMetadata {
+    pub fn from_remote_addr(remote_addr: SocketAddr) -> Self {
+        Self {
+            remote_addr: Some(remote_addr),
+            ..Default::default()
+        }
+    }
+
+    // Header names are stored lowercase
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(name.to_ascii_lowercase().as_str())
+            .map(String::as_str)
+    }
+
+    // Raw value of the first matching query parameter, not percent-decoded
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query.as_deref()?.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then_some(value)
+        })
+    }
+}
+
+// Room state owned by the runtime and passed to every hook.
+#[derive(Debug)]
+pub struct RoomContext {
+    type_: &'static str,
+    id: String,
+    tick: u64,
+    last_tick: Instant,
+    elapsed: Duration,
+    server_time: SystemTime,
+    timers: Vec<Timer>,
+    next_timer_id: u64,
+    commands: Vec<RoomCommand>,
+    seed: u64,
+    rng: RoomRng,
+    clock: Arc<dyn Clock>,
+}
+
+// Requested by hooks, applied by the runtime once the hook returned and its diffs were sent.
+#[derive(Debug, PartialEq)]
+pub enum RoomCommand {
+    Kick {
+        player_id: u64,
+        reason: String,
+    },
+    Move {
+        player_id: u64,
+        type_: String,
+        id: String,
+    },
+    Finish,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Delay {
+    Duration(Duration),
+    Ticks(u64),
+}
+
+#[derive(Debug)]
+struct Timer {
+    id: u64,
+    deadline: Deadline,
+    repeat: Option<Delay>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Deadline {
+    At(Instant),
+    Tick(u64),
+}
+
+impl RoomContext {
+    pub fn new(type_: &'static str, id: String, seed: u64, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            type_,
+            id,
+            tick: 0,
+            last_tick: clock.now(),
+            elapsed: Duration::ZERO,
+            server_time: clock.system_time(),
+            timers: vec![],
+            next_timer_id: 0,
+            commands: vec![],
+            seed,
+            rng: RoomRng::new(seed),
+            clock,
+        }
+    }
+
+    pub fn type_(&self) -> &'static str {
+        self.type_
+    }
+
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    // Replaying the room with the same seed and inputs draws the same numbers.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng(&mut self) -> &mut RoomRng {
+        &mut self.rng
+    }
+
+    // Number of ticks run so far, the current one included.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    // Time between the previous tick and the current one.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    // Wall clock time at the start of the current tick.
+    pub fn server_time(&self) -> SystemTime {
+        self.server_time
+    }
+
+    // Called by runtimes right before on_tick, or on_action for the turn runtime.
+    pub fn advance(&mut self) {
+        let now = self.clock.now();
+        self.tick += 1;
+        self.elapsed = now.duration_since(self.last_tick);
+        self.last_tick = now;
+        self.server_time = self.clock.system_time();
+    }
+
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    // Fires once through GameHooks::on_timer. Tick delays count ticks run after this one.
+    pub fn set_timeout(&mut self, delay: Delay) -> u64 {
+        self.schedule(delay, None)
+    }
+
+    pub fn set_interval(&mut self, delay: Delay) -> u64 {
+        self.schedule(delay, Some(delay))
+    }
+
+    pub fn cancel_timer(&mut self, timer_id: u64) -> bool {
+        let len = self.timers.len();
+        self.timers.retain(|timer| timer.id != timer_id);
+        self.timers.len() != len
+    }
+
+    // Closest time based deadline, runtimes sleep until then when idle.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers
+            .iter()
+            .filter_map(|timer| match timer.deadline {
+                Deadline::At(instant) => Some(instant),
+                Deadline::Tick(_) => None,
+            })
+            .min()
+    }
+
+    // Ids of the timers due, repeating ones are scheduled again.
+    pub fn expired_timers(&mut self) -> Vec<u64> {
+        let now = self.clock.now();
+        let tick = self.tick;
+        let mut expired = vec![];
+        self.timers.retain_mut(|timer| {
+            let is_expired = match timer.deadline {
+                Deadline::At(instant) => instant <= now,
+                Deadline::Tick(deadline) => deadline <= tick,
+            };
+            if !is_expired {
+                return true;
+            }
+
+            expired.push(timer.id);
+            match timer.repeat {
+                Some(delay) => {
+                    timer.deadline = Self::deadline(now, tick, delay);
+                    true
+                }
+                None => false,
+            }
+        });
+        expired
+    }
+
+    // The player leaves the room without on_leave being called, the reason is sent to its client.
+    pub fn kick(&mut self, player_id: u64, reason: impl Into<String>) {
+        self.commands.push(RoomCommand::Kick {
+            player_id,
+            reason: reason.into(),
+        });
+    }
+
+    // Leaves this room and joins an existing one, possibly of another type.
+    pub fn move_player(&mut self, player_id: u64, type_: impl Into<String>, id: impl Into<String>) {
+        self.commands.push(RoomCommand::Move {
+            player_id,
+            type_: type_.into(),
+            id: id.into(),
+        });
+    }
+
+    // Finishes the room without waiting for is_finished.
+    pub fn finish(&mut self) {
+        self.commands.push(RoomCommand::Finish);
+    }
+
+    pub fn take_commands(&mut self) -> Vec<RoomCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn schedule(&mut self, delay: Delay, repeat: Option<Delay>) -> u64 {
+        let id = self.next_timer_id;
+        self.next_timer_id += 1;
+        self.timers.push(Timer {
+            id,
+            deadline: Self::deadline(self.clock.now(), self.tick, delay),
+            repeat,
+        });
+        id
+    }
+
+    fn deadline(now: Instant, tick: u64, delay: Delay) -> Deadline {
+        match delay {
+            Delay::Duration(duration) => Deadline::At(now + duration),
+            Delay::Ticks(ticks) => Deadline::Tick(tick + ticks.max(1)),
+        }
+    }
+}
+
+// SplitMix64, small and stable across releases so recorded seeds keep replaying the same.
+#[derive(Debug, Clone)]
+pub struct RoomRng {
+    state: u64,
+}
+
+impl RoomRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in [start, end), panics on empty ranges.
+    pub fn range(&mut self, range: Range<u64>) -> u64 {
+        assert!(
+            range.start < range.end,
+            "Should never draw from an empty range"
+        );
+        // Lemire's multiply-shift, draws falling in the biased low part are rejected
+        let span = range.end - range.start;
+        let mut product = u128::from(self.next_u64()) * u128::from(span);
+        if (product as u64) < span {
+            let threshold = span.wrapping_neg() % span;
+            while (product as u64) < threshold {
+                product = u128::from(self.next_u64()) * u128::from(span);
+            }
+        }
+        range.start + (product >> 64) as u64
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
 }
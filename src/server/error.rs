@@ -16,9 +16,13 @@ pub enum ThundersServerError {
     StartFailure,
     MessageNotConnected,
     RoomNotFound,
+    PlayerNotFound,
     RoomAlreadyCreated,
     RoomTypeNotFound,
     DeserializationFailure,
+    InvalidEvent,
+    InvalidTlsConfig,
+    ShuttingDown,
 }
 
 impl Display for ThundersServerError {
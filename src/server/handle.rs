@@ -0,0 +1,160 @@
+use std::{any::Any, collections::HashMap, marker::PhantomData, sync::Arc};
+
+use crate::{
+    api::schema::{Schema, Serialize},
+    server::{
+        error::ThundersServerError,
+        hooks::DiffNotification,
+        protocol::{SessionManager, disconnect},
+        runtime::GameRuntimeAnyHandle,
+    },
+};
+
+// Out-of-band control over a running server, shares sessions and rooms with the protocols.
+pub struct ServerHandle<S>
+where
+    S: Schema,
+{
+    session_manager: Arc<SessionManager>,
+    handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+    _schema: PhantomData<fn() -> S>,
+}
+
+impl<S> Clone for ServerHandle<S>
+where
+    S: Schema,
+{
+    fn clone(&self) -> Self {
+        Self {
+            session_manager: Arc::clone(&self.session_manager),
+            handlers: Arc::clone(&self.handlers),
+            _schema: PhantomData,
+        }
+    }
+}
+
+impl<S> ServerHandle<S>
+where
+    S: Schema,
+{
+    pub(crate) fn new(
+        session_manager: Arc<SessionManager>,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+    ) -> Self {
+        Self {
+            session_manager,
+            handlers,
+            _schema: PhantomData,
+        }
+    }
+
+    // Options must serialize into the registered room type options. Without a seed a random one
+    // is picked.
+    pub fn create_room<O: Serialize<S>>(
+        &self,
+        type_: &str,
+        room_id: &str,
+        options: O,
+        seed: Option<u64>,
+    ) -> Result<(), ThundersServerError> {
+        if self.session_manager.is_closed() {
+            return Err(ThundersServerError::ShuttingDown);
+        }
+
+        let handler = self
+            .handlers
+            .get(type_)
+            .ok_or(ThundersServerError::RoomTypeNotFound)?;
+        handler.create(room_id, Some(options.serialize().as_slice()), seed)
+    }
+
+    // Players are notified as finished. Resolves once the room runtime stopped, its thread is
+    // joined off the async workers.
+    pub async fn close_room(&self, type_: &str, room_id: &str) -> Result<(), ThundersServerError> {
+        let (&type_, _) = self
+            .handlers
+            .get_key_value(type_)
+            .ok_or(ThundersServerError::RoomTypeNotFound)?;
+
+        let handlers = Arc::clone(&self.handlers);
+        let closing_id = room_id.to_string();
+        match tokio::task::spawn_blocking(move || handlers[type_].close(&closing_id)).await {
+            Ok(true) => {}
+            Ok(false) => return Err(ThundersServerError::RoomNotFound),
+            Err(_) => log::error!("Failed to finish room {type_}:{room_id}"),
+        }
+
+        self.session_manager.unsubscribe_room(type_, room_id);
+        Ok(())
+    }
+
+    // Delivered to GameHooks::on_event, fails if the event is not the room type Event.
+    pub fn send_event<E: Any + Send>(
+        &self,
+        type_: &str,
+        room_id: &str,
+        event: E,
+    ) -> Result<(), ThundersServerError> {
+        let handler = self
+            .handlers
+            .get(type_)
+            .ok_or(ThundersServerError::RoomTypeNotFound)?;
+        handler.event(room_id, Box::new(event))
+    }
+
+    // Leaves every room and closes the player connection.
+    pub fn kick(&self, player_id: u64) -> Result<(), ThundersServerError> {
+        if !self.session_manager.is_connected(player_id) {
+            return Err(ThundersServerError::PlayerNotFound);
+        }
+
+        disconnect(
+            player_id,
+            self.session_manager.as_ref(),
+            self.handlers.as_ref(),
+        );
+        Ok(())
+    }
+
+    // Delivered to the player as a diff of the given room, the player must be in it.
+    pub fn send<D: Serialize<S>>(
+        &self,
+        player_id: u64,
+        type_: &str,
+        room_id: &str,
+        delta: D,
+    ) -> Result<(), ThundersServerError> {
+        let (type_, _) = self
+            .handlers
+            .get_key_value(type_)
+            .ok_or(ThundersServerError::RoomTypeNotFound)?;
+        if !self.session_manager.is_connected(player_id)
+            || !self
+                .session_manager
+                .is_subscribed(player_id, type_, room_id)
+        {
+            return Err(ThundersServerError::PlayerNotFound);
+        }
+
+        let diff = DiffNotification::new(type_, room_id, delta.serialize());
+        self.session_manager.send(player_id, &diff);
+        Ok(())
+    }
+
+    pub fn players(&self) -> Vec<u64> {
+        self.session_manager.players()
+    }
+
+    pub fn room_types(&self) -> Vec<&'static str> {
+        self.handlers.keys().copied().collect()
+    }
+
+    pub fn rooms(&self) -> Vec<(&'static str, String)> {
+        self.handlers
+            .iter()
+            .flat_map(|(type_, handler)| {
+                handler.rooms().into_iter().map(|room_id| (*type_, room_id))
+            })
+            .collect()
+    }
+}
@@ -1,37 +1,194 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use crate::{api::message::OutputMessage, server::context::PlayerContext};
+use crate::{
+    api::message::{Delivery, OutputMessage},
+    server::context::{PlayerContext, RoomContext},
+};
 
 pub trait GameHooks: Send + 'static {
     type Delta: Send;
     type Action: Send + std::fmt::Debug;
     type Options: Default + std::fmt::Debug;
+    // Pushed by server-side code through ServerHandle::send_event. Associated type defaults are
+    // unstable so every room declares it, `()` when it takes no events.
+    type Event: Send + std::fmt::Debug + 'static;
 
     fn build(options: Self::Options) -> Self;
 
+    // Only called by runtimes with ticks, actions are empty for those handing them one by one.
+    // Rooms of turn, lockstep and relay runtimes never get it and return None.
     fn on_tick(
         &mut self,
+        room: &mut RoomContext,
         players_cxts: &HashMap<u64, Arc<PlayerContext>>,
         actions: Vec<(u64, Self::Action)>,
     ) -> Option<Vec<Diff<Self::Delta>>>;
 
-    fn on_join(&mut self, player_cxt: &PlayerContext) -> Option<Vec<Diff<Self::Delta>>>;
-    fn on_leave(&mut self, player_cxt: &PlayerContext) -> Option<Diff<Self::Delta>>;
+    fn on_join(
+        &mut self,
+        room: &mut RoomContext,
+        player_cxt: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>>;
+    fn on_leave(
+        &mut self,
+        room: &mut RoomContext,
+        player_cxt: &PlayerContext,
+    ) -> Option<Diff<Self::Delta>>;
     fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>);
+
+    // Only deltas superseding the previous ones, e.g. absolute positions, may be sent unreliable.
+    // Diffs finishing the room are always reliable.
+    fn delivery(delta: &Self::Delta) -> Delivery {
+        let _ = delta;
+        Delivery::Reliable
+    }
+
+    // Runs on the room thread like every other hook.
+    fn on_event(
+        &mut self,
+        room: &mut RoomContext,
+        players_cxts: &HashMap<u64, Arc<PlayerContext>>,
+        event: Self::Event,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        let _ = (room, players_cxts, event);
+        None
+    }
+
+    // Timers are scheduled through RoomContext::set_timeout and RoomContext::set_interval.
+    fn on_timer(
+        &mut self,
+        room: &mut RoomContext,
+        players_cxts: &HashMap<u64, Arc<PlayerContext>>,
+        timer_id: u64,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        let _ = (room, players_cxts, timer_id);
+        None
+    }
+}
+
+// Hooks handed every action on arrival instead of batched per tick, see runtime::action.
+pub trait ActionHooks: GameHooks {
+    // Refused actions never reach on_action, the player is sent the reason along with the action
+    // sequence number.
+    fn validate_action(&self, player_id: u64, action: &Self::Action) -> Result<(), String> {
+        let _ = (player_id, action);
+        Ok(())
+    }
+
+    fn on_action(
+        &mut self,
+        room: &mut RoomContext,
+        players_cxts: &HashMap<u64, Arc<PlayerContext>>,
+        player_id: u64,
+        action: Self::Action,
+    ) -> Option<Vec<Diff<Self::Delta>>>;
+}
+
+// Hooks of the turn runtime, see runtime::turn. Only actions of the current player reach
+// on_action.
+pub trait TurnHooks: ActionHooks {
+    // Player whose actions are accepted, None while nobody may play, e.g. waiting for players.
+    fn current_turn(&self) -> Option<u64>;
+
+    // Restarted whenever the turn changes hands.
+    fn turn_time_limit(&self) -> Option<Duration> {
+        None
+    }
+
+    // The turn is not passed on by itself, the hooks decide what running out of time means.
+    fn on_turn_timeout(
+        &mut self,
+        room: &mut RoomContext,
+        players_cxts: &HashMap<u64, Arc<PlayerContext>>,
+        player_id: u64,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        let _ = (room, players_cxts, player_id);
+        None
+    }
 }
 
+// Hooks of the runtimes relaying inputs simulated by the clients, actions carry the frame they
+// are meant for.
+pub trait FrameHooks: GameHooks {
+    type Input: Send + std::fmt::Debug;
+
+    // Frame the action is meant for and its input.
+    fn frame_input(action: Self::Action) -> (u64, Self::Input);
+}
+
+// Hooks of the lockstep runtime, see runtime::lockstep. The room does not simulate the game, it
+// gathers the inputs of every player for each frame and broadcasts them once the frame is done.
+pub trait LockstepHooks: FrameHooks {
+    // Inputs are ordered by player id, players that missed the frame have none.
+    fn on_frame(
+        &mut self,
+        room: &mut RoomContext,
+        players_cxts: &HashMap<u64, Arc<PlayerContext>>,
+        frame: u64,
+        inputs: Vec<(u64, Self::Input)>,
+    ) -> Option<Vec<Diff<Self::Delta>>>;
+}
+
+// Hooks of the relay runtime, see runtime::relay. Inputs are passed on as they come for clients
+// rolling back their simulation, see client::rollback.
+pub trait RelayHooks: FrameHooks {
+    // Inputs received during the server frame, in arrival order.
+    fn on_relay(
+        &mut self,
+        room: &mut RoomContext,
+        players_cxts: &HashMap<u64, Arc<PlayerContext>>,
+        server_frame: u64,
+        inputs: Vec<FrameInput<Self::Input>>,
+    ) -> Option<Vec<Diff<Self::Delta>>>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameInput<I> {
+    pub player_id: u64,
+    // Frame of the player simulation
+    pub frame: u64,
+    pub input: I,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Diff<D> {
     All { delta: D },
     TargetUnique { id: u64, delta: D },
     TargetList { ids: Vec<u64>, delta: D },
 }
 
+impl<D> Diff<D> {
+    pub fn delta(&self) -> &D {
+        match self {
+            Diff::All { delta }
+            | Diff::TargetUnique { delta, .. }
+            | Diff::TargetList { delta, .. } => delta,
+        }
+    }
+
+    // Same recipients, another delta, e.g. the serialized one.
+    pub fn map<T>(self, f: impl FnOnce(D) -> T) -> Diff<T> {
+        match self {
+            Diff::All { delta } => Diff::All { delta: f(delta) },
+            Diff::TargetUnique { id, delta } => Diff::TargetUnique {
+                id,
+                delta: f(delta),
+            },
+            Diff::TargetList { ids, delta } => Diff::TargetList {
+                ids,
+                delta: f(delta),
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct DiffNotification<'a> {
     pub type_: &'static str,
     pub id: &'a str,
     pub finished: bool,
     pub data: Vec<u8>,
+    pub ack: Option<u64>,
 }
 
 impl<'a> DiffNotification<'a> {
@@ -41,6 +198,7 @@ impl<'a> DiffNotification<'a> {
             id,
             finished: false,
             data,
+            ack: None,
         }
     }
 
@@ -50,6 +208,7 @@ impl<'a> DiffNotification<'a> {
             id,
             finished: true,
             data: vec![],
+            ack: None,
         }
     }
 }
@@ -61,6 +220,7 @@ impl<'a> From<&'a DiffNotification<'a>> for OutputMessage<'a> {
             id: val.id,
             finished: val.finished,
             data: val.data.as_slice(),
+            ack: val.ack,
         }
     }
 }
@@ -1,38 +1,80 @@
 use std::{
     collections::HashMap,
-    sync::{Arc, RwLock},
+    sync::{
+        Arc, OnceLock, RwLock, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
 use crate::{
     api::{
-        message::{InputMessage, OutputMessage},
+        message::{Delivery, InputMessage, OutputMessage},
         schema::{Deserialize, Schema, Serialize},
     },
     server::{
-        ThundersServerResult, context::PlayerContext, error::ThundersServerError,
+        ThundersServerResult,
+        context::{ConnectionMetadata, PlayerContext},
+        error::ThundersServerError,
         runtime::GameRuntimeAnyHandle,
     },
 };
 
+#[cfg(feature = "memory")]
+pub mod memory;
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "udp")]
+pub mod udp;
+#[cfg(all(unix, feature = "uds"))]
+pub mod uds;
 #[cfg(feature = "ws")]
 pub mod ws;
 
+pub type SessionMessage = (Delivery, Vec<u8>);
+
 pub trait NetworkProtocol {
     fn run<S: Schema>(
         self,
         session_manager: Arc<SessionManager>,
-        handlers: &'static HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
     ) -> impl Future<Output = ThundersServerResult>
     where
         for<'a> InputMessage<'a>: Deserialize<'a, S>;
 }
 
+// Both listeners share sessions and rooms, nest tuples to serve more than two.
+impl<A, B> NetworkProtocol for (A, B)
+where
+    A: NetworkProtocol,
+    B: NetworkProtocol,
+{
+    async fn run<S: Schema>(
+        self,
+        session_manager: Arc<SessionManager>,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+    ) -> ThundersServerResult
+    where
+        for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    {
+        let (first, second) = self;
+        futures::future::try_join(
+            first.run::<S>(Arc::clone(&session_manager), Arc::clone(&handlers)),
+            second.run::<S>(session_manager, handlers),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
 pub fn disconnect(
     p_id: u64,
     session_manager: &SessionManager,
-    handlers: &'static HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>,
+    handlers: &HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>,
 ) {
+    session_manager.close_session(p_id);
     if let Some(subscriptions) = session_manager.unsubscribe_all(p_id) {
         for (room_type, room_ids) in subscriptions {
             let handler = handlers
@@ -48,15 +90,21 @@ pub fn disconnect(
 pub fn connect<S: Schema>(
     raw_message: Vec<u8>,
     session_manager: &SessionManager,
-) -> Result<(Arc<PlayerContext>, UnboundedReceiver<Vec<u8>>), ThundersServerError>
+    metadata: ConnectionMetadata,
+    attrs: HashMap<String, String>,
+) -> Result<(Arc<PlayerContext>, UnboundedReceiver<SessionMessage>), ThundersServerError>
 where
     for<'a> InputMessage<'a>: Deserialize<'a, S>,
 {
+    if session_manager.is_closed() {
+        return Err(ThundersServerError::ShuttingDown);
+    }
+
     let raw_message_ref = raw_message.as_slice();
     if let Ok(message) = <InputMessage as Deserialize<S>>::deserialize(raw_message_ref) {
         match message {
             InputMessage::Connect { correlation_id, id } => {
-                let player_cxt = Arc::new(PlayerContext::new(id));
+                let player_cxt = Arc::new(PlayerContext::with_metadata(id, metadata, attrs));
                 Ok((player_cxt, session_manager.connect(correlation_id, id)))
             }
             _ => Err(ThundersServerError::MessageNotConnected),
@@ -70,10 +118,15 @@ pub fn process_message<S: Schema>(
     raw_message: Vec<u8>,
     player_cxt: &Arc<PlayerContext>,
     session_manager: &SessionManager,
-    handlers: &'static HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>,
+    handlers: &HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>,
 ) where
     for<'a> InputMessage<'a>: Deserialize<'a, S>,
 {
+    // Kicked players may keep sending until their transport notices the closed session
+    if !session_manager.is_connected(player_cxt.id()) {
+        return;
+    }
+
     let raw_message_ref = raw_message.as_slice();
     if let Ok(message) = <InputMessage as Deserialize<S>>::deserialize(raw_message_ref) {
         match message {
@@ -82,12 +135,15 @@ pub fn process_message<S: Schema>(
                 type_,
                 id,
                 options,
+                seed,
             } => {
-                if let Some(handler) = handlers.get(type_) {
+                if session_manager.is_closed() {
+                    session_manager.send(player_cxt.id(), ThundersServerError::ShuttingDown);
+                } else if let Some(handler) = handlers.get(type_) {
                     session_manager.subscribe(player_cxt.id(), type_, id);
 
                     // TODO: Check result to send success or not
-                    handler.register(Arc::clone(player_cxt), id, options);
+                    handler.register(Arc::clone(player_cxt), id, options, seed);
 
                     session_manager.send(
                         player_cxt.id(),
@@ -106,7 +162,9 @@ pub fn process_message<S: Schema>(
                 type_,
                 id,
             } => {
-                if let Some(handler) = handlers.get(type_) {
+                if session_manager.is_closed() {
+                    session_manager.send(player_cxt.id(), ThundersServerError::ShuttingDown);
+                } else if let Some(handler) = handlers.get(type_) {
                     session_manager.subscribe(player_cxt.id(), type_, id);
                     handler.join(Arc::clone(player_cxt), id);
 
@@ -121,9 +179,14 @@ pub fn process_message<S: Schema>(
                     session_manager.send(player_cxt.id(), ThundersServerError::RoomTypeNotFound);
                 }
             }
-            InputMessage::Action { type_, id, data } => {
+            InputMessage::Action {
+                type_,
+                id,
+                data,
+                seq,
+            } => {
                 if let Some(handler) = handlers.get(type_) {
-                    let _ = handler.action(player_cxt.id(), id, data);
+                    let _ = handler.action(player_cxt.id(), id, data, seq);
                 }
             }
             _ => {}
@@ -137,21 +200,29 @@ pub fn process_message<S: Schema>(
 // Move shared types(requests, error messages, etc...) and traits to protocol module and all related with ws to ws module.
 #[derive(Default)]
 pub struct SessionManager {
-    sessions: RwLock<HashMap<u64, UnboundedSender<Vec<u8>>>>,
+    sessions: RwLock<HashMap<u64, UnboundedSender<SessionMessage>>>,
     subscriptions: RwLock<HashMap<u64, HashMap<String, Vec<String>>>>,
+    closed: AtomicBool,
+    // Lets rooms move players to other rooms, weak since every runtime owns the session manager.
+    handlers: OnceLock<Weak<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>>,
 }
 
 impl SessionManager {
-    pub fn connect(&self, correlation_id: &str, player_id: u64) -> UnboundedReceiver<Vec<u8>> {
-        let (tx, rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    pub fn connect(
+        &self,
+        correlation_id: &str,
+        player_id: u64,
+    ) -> UnboundedReceiver<SessionMessage> {
+        let (tx, rx) = mpsc::unbounded_channel::<SessionMessage>();
 
-        tx.send(
+        tx.send((
+            Delivery::Reliable,
             OutputMessage::Connect {
                 correlation_id,
                 success: true,
             }
             .serialize(),
-        )
+        ))
         .unwrap();
         if let Ok(mut sessions) = self.sessions.write() {
             sessions.insert(player_id, tx);
@@ -176,8 +247,62 @@ impl SessionManager {
         }
     }
 
-    pub fn unsubscribe(&self, player_id: u64, type_: String, id: String) {
-        todo!()
+    pub fn unsubscribe(&self, player_id: u64, type_: &str, id: &str) {
+        if let Some(room_ids) = self
+            .subscriptions
+            .write()
+            .expect("Lock should never be poisoned")
+            .get_mut(&player_id)
+            .and_then(|subscriptions| subscriptions.get_mut(type_))
+        {
+            room_ids.retain(|room_id| room_id != id);
+        }
+    }
+
+    pub(crate) fn attach(
+        &self,
+        handlers: &Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+    ) {
+        let _ = self.handlers.set(Arc::downgrade(handlers));
+    }
+
+    // Joins a connected player to an existing room, false if any of them is gone.
+    pub fn join(&self, player_cxt: Arc<PlayerContext>, type_: &str, id: &str) -> bool {
+        let Some(handlers) = self.handlers.get().and_then(Weak::upgrade) else {
+            return false;
+        };
+        let Some(handler) = handlers.get(type_).filter(|handler| handler.contains(id)) else {
+            return false;
+        };
+
+        if let Some(subscriptions) = self
+            .subscriptions
+            .write()
+            .expect("Lock should never be poisoned")
+            .get_mut(&player_cxt.id())
+        {
+            subscriptions
+                .entry(type_.to_string())
+                .or_default()
+                .push(id.to_string());
+        } else {
+            return false;
+        }
+
+        handler.join(player_cxt, id);
+        true
+    }
+
+    pub fn unsubscribe_room(&self, type_: &str, id: &str) {
+        let mut subscriptions = self
+            .subscriptions
+            .write()
+            .expect("Lock should never be poisoned");
+        for player_subscriptions in subscriptions.values_mut() {
+            if let Some(room_ids) = player_subscriptions.get_mut(type_) {
+                room_ids.retain(|room_id| room_id != id);
+            }
+        }
     }
 
     pub fn unsubscribe_all(&self, player_id: u64) -> Option<HashMap<String, Vec<String>>> {
@@ -187,11 +312,79 @@ impl SessionManager {
             .remove(&player_id)
     }
 
+    pub fn is_connected(&self, player_id: u64) -> bool {
+        self.sessions
+            .read()
+            .expect("Lock should never be poisoned")
+            .contains_key(&player_id)
+    }
+
+    pub fn is_subscribed(&self, player_id: u64, type_: &str, id: &str) -> bool {
+        self.subscriptions
+            .read()
+            .expect("Lock should never be poisoned")
+            .get(&player_id)
+            .and_then(|subscriptions| subscriptions.get(type_))
+            .is_some_and(|room_ids| room_ids.iter().any(|room_id| room_id == id))
+    }
+
+    pub fn players(&self) -> Vec<u64> {
+        self.sessions
+            .read()
+            .expect("Lock should never be poisoned")
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    // Dropping the sender lets the transport flush what is queued, then every transport ends the
+    // connection and disconnects the player.
+    pub fn close_session(&self, player_id: u64) -> bool {
+        self.sessions
+            .write()
+            .expect("Lock should never be poisoned")
+            .remove(&player_id)
+            .is_some()
+    }
+
+    // Rejects new connections, rooms and joins from now on.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    pub fn disconnect_all(&self) {
+        self.sessions
+            .write()
+            .expect("Lock should never be poisoned")
+            .clear();
+    }
+
+    // True once every connected player went through disconnect.
+    pub fn is_drained(&self) -> bool {
+        self.subscriptions
+            .read()
+            .expect("Lock should never be poisoned")
+            .is_empty()
+    }
+
     pub fn send<'a>(&self, player_id: u64, message: impl Into<OutputMessage<'a>>) {
+        self.send_with_delivery(player_id, message, Delivery::Reliable);
+    }
+
+    pub fn send_with_delivery<'a>(
+        &self,
+        player_id: u64,
+        message: impl Into<OutputMessage<'a>>,
+        delivery: Delivery,
+    ) {
         if let Ok(sessions) = self.sessions.read()
             && let Some(session) = sessions.get(&player_id)
         {
-            let _ = session.send(message.into().serialize());
+            let _ = session.send((delivery, message.into().serialize()));
         }
     }
 
@@ -199,6 +392,15 @@ impl SessionManager {
         &self,
         player_ids: impl Iterator<Item = &'a u64>,
         message: impl Into<OutputMessage<'a>>,
+    ) {
+        self.send_all_with_delivery(player_ids, message, Delivery::Reliable);
+    }
+
+    pub fn send_all_with_delivery<'a>(
+        &self,
+        player_ids: impl Iterator<Item = &'a u64>,
+        message: impl Into<OutputMessage<'a>>,
+        delivery: Delivery,
     ) {
         let raw_message = message.into().serialize();
 
@@ -206,7 +408,7 @@ impl SessionManager {
             if let Ok(sessions) = self.sessions.read()
                 && let Some(session) = sessions.get(p_id)
             {
-                let _ = session.send(raw_message.clone());
+                let _ = session.send((delivery, raw_message.clone()));
             }
         }
     }
@@ -0,0 +1,123 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    api::{
+        memory::{self, MemoryConnection, MemoryConnector, MemoryListener},
+        message::{InputMessage, OutputMessage},
+        schema::{Deserialize, Schema, Serialize},
+    },
+    server::{
+        ThundersServerResult,
+        context::ConnectionMetadata,
+        error::ThundersServerError,
+        protocol::{NetworkProtocol, SessionManager, connect, disconnect, process_message},
+        runtime::GameRuntimeAnyHandle,
+    },
+};
+
+pub struct MemoryProtocol {
+    listener: MemoryListener,
+    connector: MemoryConnector,
+}
+
+impl MemoryProtocol {
+    pub fn new() -> Self {
+        let (listener, connector) = memory::listener();
+        Self {
+            listener,
+            connector,
+        }
+    }
+
+    // Clients connect through it with `MemoryClientProtocol`, the server stops once every connector is dropped.
+    pub fn connector(&self) -> MemoryConnector {
+        self.connector.clone()
+    }
+}
+
+impl Default for MemoryProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetworkProtocol for MemoryProtocol {
+    async fn run<S: Schema>(
+        self,
+        session_manager: Arc<SessionManager>,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+    ) -> ThundersServerResult
+    where
+        for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    {
+        let Self {
+            mut listener,
+            connector,
+        } = self;
+        drop(connector);
+
+        while let Some(connection) = listener.accept().await {
+            let session_manager = Arc::clone(&session_manager);
+            let handlers = Arc::clone(&handlers);
+            tokio::spawn(async move {
+                let MemoryConnection {
+                    sender,
+                    mut receiver,
+                } = connection;
+                let player_cxt;
+                let mut writer;
+                if let Some(raw_message) = receiver.recv().await {
+                    match connect::<S>(
+                        raw_message,
+                        session_manager.as_ref(),
+                        ConnectionMetadata::default(),
+                        HashMap::new(),
+                    ) {
+                        Ok((cxt, mut session)) => {
+                            player_cxt = cxt;
+                            // Dropping the sender once the session closed lets the client notice
+                            writer = tokio::spawn(async move {
+                                while let Some((_, raw_message)) = session.recv().await {
+                                    if sender.send(raw_message).is_err() {
+                                        break;
+                                    }
+                                }
+                            });
+                        }
+                        Err(err) => {
+                            let output_message: OutputMessage<'_> = err.into();
+                            let _ = sender.send(output_message.serialize());
+                            return;
+                        }
+                    }
+                } else {
+                    let output_message: OutputMessage<'_> =
+                        ThundersServerError::MessageNotConnected.into();
+                    let _ = sender.send(output_message.serialize());
+                    return;
+                }
+
+                loop {
+                    tokio::select! {
+                        raw_message = receiver.recv() => match raw_message {
+                            Some(raw_message) => process_message::<S>(
+                                raw_message,
+                                &player_cxt,
+                                session_manager.as_ref(),
+                                handlers.as_ref(),
+                            ),
+                            None => break,
+                        },
+                        // Session closed by the server
+                        _ = &mut writer => break,
+                    }
+                }
+
+                writer.abort();
+                disconnect(player_cxt.id(), session_manager.as_ref(), handlers.as_ref());
+            });
+        }
+
+        Ok(())
+    }
+}
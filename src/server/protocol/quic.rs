@@ -0,0 +1,156 @@
+use std::{collections::HashMap, sync::Arc};
+
+use quinn::{
+    Endpoint, ServerConfig,
+    rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+};
+
+use crate::{
+    api::{
+        framing::{read_frame, write_frame},
+        message::{Delivery, InputMessage, OutputMessage},
+        schema::{Deserialize, Schema, Serialize},
+    },
+    server::{
+        ThundersServerResult,
+        context::ConnectionMetadata,
+        error::ThundersServerError,
+        protocol::{NetworkProtocol, SessionManager, connect, disconnect, process_message},
+        runtime::GameRuntimeAnyHandle,
+    },
+};
+
+pub struct QuicProtocol {
+    addr: String,
+    port: u16,
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl QuicProtocol {
+    // Starts with a self-signed certificate for "localhost", meant for local testing.
+    pub fn new(addr: impl Into<String>, port: u16) -> Self {
+        let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+            .expect("Should always generate self-signed certificate");
+        let key = PrivatePkcs8KeyDer::from(certified_key.signing_key.serialize_der());
+
+        Self {
+            addr: addr.into(),
+            port,
+            cert_chain: vec![certified_key.cert.der().clone()],
+            key: key.into(),
+        }
+    }
+
+    pub fn with_certificate(
+        mut self,
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Self {
+        self.cert_chain = cert_chain;
+        self.key = key;
+        self
+    }
+
+    // Leaf certificate, clients must trust it when self-signed.
+    pub fn certificate(&self) -> &CertificateDer<'static> {
+        self.cert_chain
+            .first()
+            .expect("Should always have at least one certificate")
+    }
+}
+
+impl NetworkProtocol for QuicProtocol {
+    async fn run<S: Schema>(
+        self,
+        session_manager: Arc<SessionManager>,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+    ) -> ThundersServerResult
+    where
+        for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    {
+        let addr = tokio::net::lookup_host((self.addr.as_str(), self.port))
+            .await
+            .map_err(|_| ThundersServerError::StartFailure)?
+            .next()
+            .ok_or(ThundersServerError::StartFailure)?;
+        let server_config = ServerConfig::with_single_cert(self.cert_chain, self.key)
+            .map_err(|_| ThundersServerError::StartFailure)?;
+        let endpoint =
+            Endpoint::server(server_config, addr).map_err(|_| ThundersServerError::StartFailure)?;
+
+        while let Some(incoming) = endpoint.accept().await {
+            let session_manager = Arc::clone(&session_manager);
+            let handlers = Arc::clone(&handlers);
+            tokio::spawn(async move {
+                let player_cxt;
+                let Ok(connection) = incoming.await else {
+                    return;
+                };
+                let Ok((mut send, mut recv)) = connection.accept_bi().await else {
+                    return;
+                };
+
+                if let Ok(raw_message) = read_frame(&mut recv).await {
+                    match connect::<S>(
+                        raw_message,
+                        session_manager.as_ref(),
+                        ConnectionMetadata::from_remote_addr(connection.remote_address()),
+                        HashMap::new(),
+                    ) {
+                        Ok((cxt, mut receiver)) => {
+                            player_cxt = cxt;
+                            let connection = connection.clone();
+                            tokio::spawn(async move {
+                                while let Some((delivery, raw_message)) = receiver.recv().await {
+                                    // Diffs go through datagrams when they fit, everything else through the control stream
+                                    if delivery == Delivery::Unreliable
+                                        && connection
+                                            .max_datagram_size()
+                                            .is_some_and(|max_size| raw_message.len() <= max_size)
+                                    {
+                                        if connection.send_datagram(raw_message.into()).is_err() {
+                                            break;
+                                        }
+                                    } else if write_frame(&mut send, &raw_message).await.is_err() {
+                                        break;
+                                    }
+                                }
+
+                                // Session closed by the server
+                                let _ = send.finish();
+                                let _ = send.stopped().await;
+                                connection.close(0u32.into(), b"");
+                            });
+                        }
+                        Err(err) => {
+                            let output_message: OutputMessage<'_> = err.into();
+                            let _ = write_frame(&mut send, &output_message.serialize()).await;
+                            let _ = send.finish();
+                            let _ = send.stopped().await;
+                            return;
+                        }
+                    }
+                } else {
+                    let output_message: OutputMessage<'_> =
+                        ThundersServerError::MessageNotConnected.into();
+                    let _ = write_frame(&mut send, &output_message.serialize()).await;
+                    return;
+                }
+
+                while let Ok(raw_message) = read_frame(&mut recv).await {
+                    process_message::<S>(
+                        raw_message,
+                        &player_cxt,
+                        session_manager.as_ref(),
+                        handlers.as_ref(),
+                    );
+                }
+
+                disconnect(player_cxt.id(), session_manager.as_ref(), handlers.as_ref());
+            });
+        }
+
+        Ok(())
+    }
+}
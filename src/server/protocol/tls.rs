@@ -0,0 +1,57 @@
+use std::{path::Path, sync::Arc};
+
+use rustls::{
+    ServerConfig,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+};
+use tokio_rustls::TlsAcceptor;
+
+use crate::server::error::ThundersServerError;
+
+pub struct ServerTlsConfig {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl ServerTlsConfig {
+    pub fn new(cert_chain: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Self {
+        Self { cert_chain, key }
+    }
+
+    pub fn from_pem(cert_chain_pem: &[u8], key_pem: &[u8]) -> Result<Self, ThundersServerError> {
+        let cert_chain = CertificateDer::pem_slice_iter(cert_chain_pem)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ThundersServerError::InvalidTlsConfig)?;
+        let key = PrivateKeyDer::from_pem_slice(key_pem)
+            .map_err(|_| ThundersServerError::InvalidTlsConfig)?;
+        Ok(Self::new(cert_chain, key))
+    }
+
+    pub fn from_pem_files(
+        cert_chain_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self, ThundersServerError> {
+        let cert_chain = CertificateDer::pem_file_iter(cert_chain_path)
+            .map_err(|_| ThundersServerError::InvalidTlsConfig)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ThundersServerError::InvalidTlsConfig)?;
+        let key = PrivateKeyDer::from_pem_file(key_path)
+            .map_err(|_| ThundersServerError::InvalidTlsConfig)?;
+        Ok(Self::new(cert_chain, key))
+    }
+
+    pub(crate) fn into_acceptor(self) -> Result<TlsAcceptor, ThundersServerError> {
+        if self.cert_chain.is_empty() {
+            return Err(ThundersServerError::InvalidTlsConfig);
+        }
+
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|_| ThundersServerError::InvalidTlsConfig)?
+            .with_no_client_auth()
+            .with_single_cert(self.cert_chain, self.key)
+            .map_err(|_| ThundersServerError::InvalidTlsConfig)?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
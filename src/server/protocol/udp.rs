@@ -0,0 +1,238 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::{net::UdpSocket, sync::mpsc, task::JoinHandle};
+
+use crate::{
+    api::{
+        message::{Delivery, InputMessage, OutputMessage},
+        schema::{Deserialize, Schema, Serialize},
+        udp::{Connection, Packet, UdpSettings},
+    },
+    server::{
+        ThundersServerResult,
+        context::{ConnectionMetadata, PlayerContext},
+        error::ThundersServerError,
+        protocol::{
+            NetworkProtocol, SessionManager, SessionMessage, connect, disconnect, process_message,
+        },
+        runtime::GameRuntimeAnyHandle,
+    },
+};
+
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+pub struct UdpProtocol {
+    addr: String,
+    port: u16,
+    settings: UdpSettings,
+}
+
+impl UdpProtocol {
+    pub fn new(addr: impl Into<String>, port: u16) -> Self {
+        Self {
+            addr: addr.into(),
+            port,
+            settings: UdpSettings::default(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: UdpSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+struct Peer {
+    nonce: u64,
+    connection: Connection,
+    player_cxt: Option<Arc<PlayerContext>>,
+    forwarder: Option<JoinHandle<()>>,
+    // Session closed by the server, the peer is dropped once its reliable messages are acked
+    closing: bool,
+}
+
+impl Peer {
+    fn close(
+        self,
+        session_manager: &SessionManager,
+        handlers: &HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>,
+    ) {
+        if let Some(forwarder) = self.forwarder {
+            forwarder.abort();
+        }
+        if let Some(player_cxt) = self.player_cxt {
+            disconnect(player_cxt.id(), session_manager, handlers);
+        }
+    }
+}
+
+impl NetworkProtocol for UdpProtocol {
+    async fn run<S: Schema>(
+        self,
+        session_manager: Arc<SessionManager>,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+    ) -> ThundersServerResult
+    where
+        for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    {
+        let socket = UdpSocket::bind(format!("{}:{}", self.addr, self.port).as_str())
+            .await
+            .map_err(|_| ThundersServerError::StartFailure)?;
+
+        // Forwarders send None once the session closed, tagged with the peer nonce so messages of
+        // a replaced peer are dropped
+        let (outbound_tx, mut outbound_rx) =
+            mpsc::unbounded_channel::<(SocketAddr, u64, Option<SessionMessage>)>();
+        let mut peers: HashMap<SocketAddr, Peer> = HashMap::new();
+        let mut update_interval =
+            tokio::time::interval(Duration::from_millis(self.settings.update_millis));
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+
+        loop {
+            tokio::select! {
+                result = socket.recv_from(&mut buf) => {
+                    let Ok((len, addr)) = result else {
+                        // Errors like ICMP port unreachable from a gone peer must not stop the server
+                        continue;
+                    };
+                    let Some(packet) = Packet::decode(&buf[..len]) else {
+                        continue;
+                    };
+
+                    match packet {
+                        Packet::Handshake { protocol_id, nonce } => {
+                            if protocol_id != self.settings.protocol_id {
+                                continue;
+                            }
+                            // A new nonce means the client restarted, its previous connection is stale
+                            if peers.get(&addr).is_none_or(|peer| peer.nonce != nonce) {
+                                let peer = Peer {
+                                    nonce,
+                                    connection: Connection::new(&self.settings),
+                                    player_cxt: None,
+                                    forwarder: None,
+                                    closing: false,
+                                };
+                                if let Some(stale) = peers.insert(addr, peer) {
+                                    log::debug!("Replaced stale UDP peer. Address: {addr}");
+                                    stale.close(session_manager.as_ref(), handlers.as_ref());
+                                }
+                            }
+                            let accept = Packet::HandshakeAccept { protocol_id, nonce }.encode();
+                            let _ = socket.send_to(&accept, addr).await;
+                        }
+                        Packet::Disconnect => {
+                            if let Some(peer) = peers.remove(&addr) {
+                                peer.close(session_manager.as_ref(), handlers.as_ref());
+                            }
+                        }
+                        Packet::HandshakeAccept { .. } => {}
+                        packet => {
+                            let Some(peer) = peers.get_mut(&addr) else {
+                                continue;
+                            };
+
+                            let raw_messages = peer.connection.receive(packet);
+                            if peer.closing {
+                                continue;
+                            }
+                            for raw_message in raw_messages {
+                                if let Some(player_cxt) = peer.player_cxt.as_ref() {
+                                    process_message::<S>(
+                                        raw_message,
+                                        player_cxt,
+                                        session_manager.as_ref(),
+                                        handlers.as_ref(),
+                                    );
+                                    continue;
+                                }
+
+                                match connect::<S>(
+                                    raw_message,
+                                    session_manager.as_ref(),
+                                    ConnectionMetadata::from_remote_addr(addr),
+                                    HashMap::new(),
+                                ) {
+                                    Ok((cxt, mut receiver)) => {
+                                        peer.player_cxt = Some(cxt);
+                                        let outbound_tx = outbound_tx.clone();
+                                        let nonce = peer.nonce;
+                                        peer.forwarder = Some(tokio::spawn(async move {
+                                            while let Some(message) = receiver.recv().await {
+                                                if outbound_tx
+                                                    .send((addr, nonce, Some(message)))
+                                                    .is_err()
+                                                {
+                                                    return;
+                                                }
+                                            }
+                                            let _ = outbound_tx.send((addr, nonce, None));
+                                        }));
+                                    }
+                                    Err(err) => {
+                                        let output_message: OutputMessage<'_> = err.into();
+                                        for datagram in peer
+                                            .connection
+                                            .send(&output_message.serialize(), Delivery::Reliable)
+                                        {
+                                            let _ = socket.send_to(&datagram, addr).await;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Some((addr, nonce, message)) = outbound_rx.recv() => {
+                    let Some(peer) = peers.get_mut(&addr).filter(|peer| peer.nonce == nonce) else {
+                        continue;
+                    };
+                    match message {
+                        Some((delivery, raw_message)) => {
+                            for datagram in peer.connection.send(&raw_message, delivery) {
+                                let _ = socket.send_to(&datagram, addr).await;
+                            }
+                        }
+                        None => {
+                            peer.closing = true;
+                            if let Some(player_cxt) = peer.player_cxt.take() {
+                                disconnect(player_cxt.id(), session_manager.as_ref(), handlers.as_ref());
+                            }
+                        }
+                    }
+                }
+                _ = update_interval.tick() => {
+                    let idle_peers = peers
+                        .iter()
+                        .filter(|(_, peer)| peer.connection.is_idle())
+                        .map(|(addr, _)| *addr)
+                        .collect::<Vec<_>>();
+                    for addr in idle_peers {
+                        if let Some(peer) = peers.remove(&addr) {
+                            log::debug!("UDP peer timed out. Address: {addr}");
+                            peer.close(session_manager.as_ref(), handlers.as_ref());
+                        }
+                    }
+
+                    let closed_peers = peers
+                        .iter()
+                        .filter(|(_, peer)| peer.closing && !peer.connection.has_pending())
+                        .map(|(addr, _)| *addr)
+                        .collect::<Vec<_>>();
+                    for addr in closed_peers {
+                        if let Some(peer) = peers.remove(&addr) {
+                            let _ = socket.send_to(&Packet::Disconnect.encode(), addr).await;
+                            peer.close(session_manager.as_ref(), handlers.as_ref());
+                        }
+                    }
+
+                    for (addr, peer) in peers.iter_mut() {
+                        for datagram in peer.connection.update() {
+                            let _ = socket.send_to(&datagram, addr).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::net::UnixListener;
+
+use crate::{
+    api::{
+        framing::{read_frame, write_frame},
+        message::{InputMessage, OutputMessage},
+        schema::{Deserialize, Schema, Serialize},
+    },
+    server::{
+        ThundersServerResult,
+        context::ConnectionMetadata,
+        error::ThundersServerError,
+        protocol::{NetworkProtocol, SessionManager, connect, disconnect, process_message},
+        runtime::GameRuntimeAnyHandle,
+    },
+};
+
+pub struct UnixSocketProtocol {
+    path: PathBuf,
+}
+
+impl UnixSocketProtocol {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl NetworkProtocol for UnixSocketProtocol {
+    async fn run<S: Schema>(
+        self,
+        session_manager: Arc<SessionManager>,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+    ) -> ThundersServerResult
+    where
+        for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    {
+        // Socket files outlive the process, only a stale socket is replaced, never a regular file
+        if let Ok(metadata) = std::fs::symlink_metadata(&self.path)
+            && metadata.file_type().is_socket()
+        {
+            std::fs::remove_file(&self.path).map_err(|_| ThundersServerError::StartFailure)?;
+        }
+
+        let listener =
+            UnixListener::bind(&self.path).map_err(|_| ThundersServerError::StartFailure)?;
+
+        loop {
+            let session_manager = Arc::clone(&session_manager);
+            let handlers = Arc::clone(&handlers);
+            if let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let player_cxt;
+                    let mut writer;
+                    let (mut read, mut write) = stream.into_split();
+
+                    if let Ok(raw_message) = read_frame(&mut read).await {
+                        match connect::<S>(
+                            raw_message,
+                            session_manager.as_ref(),
+                            ConnectionMetadata::default(),
+                            HashMap::new(),
+                        ) {
+                            Ok((cxt, mut receiver)) => {
+                                player_cxt = cxt;
+                                // Dropping the write half once the session closed lets the client notice
+                                writer = tokio::spawn(async move {
+                                    while let Some((_, raw_message)) = receiver.recv().await {
+                                        if write_frame(&mut write, &raw_message).await.is_err() {
+                                            break;
+                                        }
+                                    }
+                                });
+                            }
+                            Err(err) => {
+                                let output_message: OutputMessage<'_> = err.into();
+                                let _ = write_frame(&mut write, &output_message.serialize()).await;
+                                return;
+                            }
+                        }
+                    } else {
+                        let output_message: OutputMessage<'_> =
+                            ThundersServerError::MessageNotConnected.into();
+                        let _ = write_frame(&mut write, &output_message.serialize()).await;
+                        return;
+                    }
+
+                    loop {
+                        tokio::select! {
+                            raw_message = read_frame(&mut read) => match raw_message {
+                                Ok(raw_message) => process_message::<S>(
+                                    raw_message,
+                                    &player_cxt,
+                                    session_manager.as_ref(),
+                                    handlers.as_ref(),
+                                ),
+                                Err(_) => break,
+                            },
+                            // Session closed by the server
+                            _ = &mut writer => break,
+                        }
+                    }
+
+                    writer.abort();
+                    disconnect(player_cxt.id(), session_manager.as_ref(), handlers.as_ref());
+                });
+            } else {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,17 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
-use futures::{SinkExt, StreamExt};
-use tokio::net::TcpListener;
+use futures::{SinkExt, Stream, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+};
 use tokio_tungstenite::{
-    accept_async,
-    tungstenite::{Bytes, Message, Utf8Bytes},
+    accept_hdr_async,
+    tungstenite::{
+        Bytes, Error, Message, Utf8Bytes,
+        handshake::server::{ErrorResponse, Request, Response},
+        http::{HeaderValue, StatusCode, header::SEC_WEBSOCKET_PROTOCOL},
+    },
 };
 
 use crate::{
@@ -14,15 +21,65 @@ use crate::{
     },
     server::{
         ThundersServerResult,
+        context::ConnectionMetadata,
         error::ThundersServerError,
         protocol::{NetworkProtocol, SessionManager, connect, disconnect, process_message},
         runtime::GameRuntimeAnyHandle,
     },
 };
 
+#[cfg(feature = "tls")]
+use crate::server::protocol::tls::ServerTlsConfig;
+
+pub type HandshakeCallback = Arc<
+    dyn Fn(&ConnectionMetadata) -> Result<HashMap<String, String>, HandshakeRejection>
+        + Send
+        + Sync,
+>;
+
+pub struct HandshakeRejection {
+    pub status: u16,
+    pub reason: String,
+}
+
+impl HandshakeRejection {
+    pub fn new(status: u16, reason: impl Into<String>) -> Self {
+        Self {
+            status,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn unauthorized(reason: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED.as_u16(), reason)
+    }
+
+    pub fn not_found(reason: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND.as_u16(), reason)
+    }
+
+    fn into_response(self) -> ErrorResponse {
+        let mut response = ErrorResponse::new(Some(self.reason));
+        *response.status_mut() = StatusCode::from_u16(self.status).unwrap_or(StatusCode::FORBIDDEN);
+        response
+    }
+}
+
 pub struct WebSocketProtocol {
     addr: String,
     port: u16,
+    handshake: Option<HandshakeCallback>,
+    subprotocols: Vec<String>,
+    heartbeat: Heartbeat,
+    #[cfg(feature = "tls")]
+    tls_config: Option<ServerTlsConfig>,
+}
+
+// Pings are sent every interval, peers silent for longer than the idle timeout are disconnected.
+#[derive(Clone, Copy)]
+struct Heartbeat {
+    interval: Duration,
+    idle_timeout: Duration,
 }
 
 impl WebSocketProtocol {
@@ -30,82 +87,109 @@ impl WebSocketProtocol {
         Self {
             addr: addr.into(),
             port,
+            handshake: None,
+            subprotocols: vec![],
+            heartbeat: Heartbeat {
+                interval: Duration::from_secs(15),
+                idle_timeout: Duration::from_secs(45),
+            },
+            #[cfg(feature = "tls")]
+            tls_config: None,
         }
     }
+
+    // Called with the upgrade request before accepting it. Returned attributes are attached to the
+    // player context, a rejection answers the upgrade with its status and reason.
+    pub fn with_handshake<F>(mut self, handshake: F) -> Self
+    where
+        F: Fn(&ConnectionMetadata) -> Result<HashMap<String, String>, HandshakeRejection>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.handshake = Some(Arc::new(handshake));
+        self
+    }
+
+    // The first subprotocol offered by the client that is supported here gets selected.
+    pub fn with_subprotocol(mut self, subprotocol: impl Into<String>) -> Self {
+        self.subprotocols.push(subprotocol.into());
+        self
+    }
+
+    pub fn with_heartbeat(mut self, interval: Duration, idle_timeout: Duration) -> Self {
+        self.heartbeat = Heartbeat {
+            interval,
+            idle_timeout,
+        };
+        self
+    }
+
+    // Serves wss:// instead of ws://
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls_config: ServerTlsConfig) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
 }
 
 impl NetworkProtocol for WebSocketProtocol {
     async fn run<S: Schema>(
         self,
         session_manager: Arc<SessionManager>,
-        handlers: &'static HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>,
+        handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
     ) -> ThundersServerResult
     where
         for<'a> InputMessage<'a>: Deserialize<'a, S>,
     {
+        #[cfg(feature = "tls")]
+        let tls_acceptor = self
+            .tls_config
+            .map(ServerTlsConfig::into_acceptor)
+            .transpose()?;
+
         let listener = TcpListener::bind(format!("{}:{}", self.addr, self.port).as_str())
             .await
             .map_err(|_| ThundersServerError::StartFailure)?;
+        let subprotocols = Arc::new(self.subprotocols);
+        let heartbeat = self.heartbeat;
 
         loop {
             let session_manager = Arc::clone(&session_manager);
-            if let Ok((stream, _)) = listener.accept().await {
-                tokio::spawn(async move {
-                    let player_cxt;
-                    let ws_stream = match accept_async(stream).await {
-                        Ok(ws_stream) => ws_stream,
-                        Err(_) => {
-                            return;
-                        }
-                    };
-                    let (mut write, mut read) = ws_stream.split();
-
-                    if let Some(Ok(msg)) = read.next().await {
-                        let raw_message: Vec<u8> = message_into_bytes(msg);
-                        match connect(raw_message, session_manager.as_ref()) {
-                            Ok((cxt, mut receiver)) => {
-                                player_cxt = cxt;
-                                tokio::spawn(async move {
-                                    while let Some(raw_message) = receiver.recv().await {
-                                        if write
-                                            .send(bytes_into_message::<S>(raw_message))
-                                            .await
-                                            .is_err()
-                                        {
-                                            break;
-                                        }
-                                    }
-                                });
-                            }
-                            Err(err) => {
-                                let output_message: OutputMessage<'_> = err.into();
-                                let _ = write
-                                    .send(bytes_into_message::<S>(output_message.serialize()))
-                                    .await;
-                                return;
-                            }
-                        }
-                    } else {
-                        let output_message: OutputMessage<'_> =
-                            ThundersServerError::MessageNotConnected.into();
-                        let _ = write
-                            .send(bytes_into_message::<S>(output_message.serialize()))
+            let handlers = Arc::clone(&handlers);
+            let handshake = self.handshake.clone();
+            let subprotocols = Arc::clone(&subprotocols);
+            if let Ok((stream, remote_addr)) = listener.accept().await {
+                #[cfg(feature = "tls")]
+                if let Some(tls_acceptor) = tls_acceptor.clone() {
+                    tokio::spawn(async move {
+                        if let Ok(stream) = tls_acceptor.accept(stream).await {
+                            serve::<S, _>(
+                                stream,
+                                remote_addr,
+                                handshake,
+                                subprotocols,
+                                heartbeat,
+                                session_manager,
+                                handlers,
+                            )
                             .await;
-                        return;
-                    }
-
-                    while let Some(Ok(msg)) = read.next().await {
-                        let raw_message: Vec<u8> = message_into_bytes(msg);
-
-                        process_message(
-                            raw_message,
-                            &player_cxt,
-                            session_manager.as_ref(),
-                            handlers,
-                        );
-                    }
+                        }
+                    });
+                    continue;
+                }
 
-                    disconnect(player_cxt.id(), session_manager.as_ref(), handlers);
+                tokio::spawn(async move {
+                    serve::<S, _>(
+                        stream,
+                        remote_addr,
+                        handshake,
+                        subprotocols,
+                        heartbeat,
+                        session_manager,
+                        handlers,
+                    )
+                    .await;
                 });
             } else {
                 // Check tcp stream closed error
@@ -117,6 +201,143 @@ impl NetworkProtocol for WebSocketProtocol {
     }
 }
 
+async fn serve<S, T>(
+    stream: T,
+    remote_addr: SocketAddr,
+    handshake: Option<HandshakeCallback>,
+    subprotocols: Arc<Vec<String>>,
+    heartbeat: Heartbeat,
+    session_manager: Arc<SessionManager>,
+    handlers: Arc<HashMap<&'static str, Box<dyn GameRuntimeAnyHandle>>>,
+) where
+    S: Schema,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    for<'a> InputMessage<'a>: Deserialize<'a, S>,
+{
+    let player_cxt;
+    let mut metadata = ConnectionMetadata::from_remote_addr(remote_addr);
+    let mut attrs = HashMap::new();
+    // The error response type is imposed by tungstenite
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, mut response: Response| {
+        metadata.path = Some(request.uri().path().to_string());
+        metadata.query = request.uri().query().map(str::to_string);
+        metadata.headers = request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.as_str().to_string(), value.to_string()))
+            })
+            .collect();
+
+        if let Some(handshake) = handshake.as_ref() {
+            attrs = handshake(&metadata).map_err(HandshakeRejection::into_response)?;
+        }
+
+        let selected = request
+            .headers()
+            .get_all(SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .find(|offered| subprotocols.iter().any(|supported| supported == offered));
+        if let Some(selected) = selected.and_then(|selected| HeaderValue::from_str(selected).ok()) {
+            response
+                .headers_mut()
+                .insert(SEC_WEBSOCKET_PROTOCOL, selected);
+        }
+        Ok(response)
+    };
+
+    let ws_stream = match accept_hdr_async(stream, callback).await {
+        Ok(ws_stream) => ws_stream,
+        Err(_) => {
+            return;
+        }
+    };
+    let (mut write, mut read) = ws_stream.split();
+
+    let writer;
+    if let Some(raw_message) = next_payload(&mut read, heartbeat.idle_timeout).await {
+        match connect::<S>(raw_message, session_manager.as_ref(), metadata, attrs) {
+            Ok((cxt, mut receiver)) => {
+                player_cxt = cxt;
+                writer = tokio::spawn(async move {
+                    let mut ping_interval = tokio::time::interval_at(
+                        tokio::time::Instant::now() + heartbeat.interval,
+                        heartbeat.interval,
+                    );
+                    loop {
+                        let message = tokio::select! {
+                            message = receiver.recv() => match message {
+                                Some((_, raw_message)) => bytes_into_message::<S>(raw_message),
+                                // Session closed by the server
+                                None => {
+                                    let _ = write.send(Message::Close(None)).await;
+                                    break;
+                                }
+                            },
+                            _ = ping_interval.tick() => Message::Ping(Bytes::new()),
+                        };
+                        if write.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            Err(err) => {
+                let output_message: OutputMessage<'_> = err.into();
+                let _ = write
+                    .send(bytes_into_message::<S>(output_message.serialize()))
+                    .await;
+                return;
+            }
+        }
+    } else {
+        let output_message: OutputMessage<'_> = ThundersServerError::MessageNotConnected.into();
+        let _ = write
+            .send(bytes_into_message::<S>(output_message.serialize()))
+            .await;
+        return;
+    }
+
+    while let Some(raw_message) = next_payload(&mut read, heartbeat.idle_timeout).await {
+        process_message::<S>(
+            raw_message,
+            &player_cxt,
+            session_manager.as_ref(),
+            handlers.as_ref(),
+        );
+    }
+
+    writer.abort();
+    disconnect(player_cxt.id(), session_manager.as_ref(), handlers.as_ref());
+}
+
+// Skips control frames, pings are answered by tungstenite itself. Returns None once the peer
+// closes, fails or stays silent for longer than the idle timeout.
+async fn next_payload<R>(read: &mut R, idle_timeout: Duration) -> Option<Vec<u8>>
+where
+    R: Stream<Item = Result<Message, Error>> + Unpin,
+{
+    loop {
+        match tokio::time::timeout(idle_timeout, read.next()).await {
+            Ok(Some(Ok(Message::Binary(bytes)))) => return Some(bytes.into()),
+            Ok(Some(Ok(Message::Text(bytes)))) => return Some(Bytes::from(bytes).into()),
+            Ok(Some(Ok(Message::Close(_)))) | Ok(Some(Err(_))) | Ok(None) => return None,
+            Ok(Some(Ok(_))) => continue,
+            Err(_) => {
+                log::warn!("Websocket peer timed out");
+                return None;
+            }
+        }
+    }
+}
+
 fn bytes_into_message<S: Schema>(raw_message: Vec<u8>) -> Message {
     match S::schema_type() {
         SchemaType::Text => {
@@ -128,13 +349,3 @@ fn bytes_into_message<S: Schema>(raw_message: Vec<u8>) -> Message {
         SchemaType::Binary => Message::Binary(raw_message.into()),
     }
 }
-
-fn message_into_bytes(message: Message) -> Vec<u8> {
-    match message {
-        Message::Binary(bytes) => bytes.into(),
-        Message::Text(bytes) => Bytes::from(bytes).into(),
-        _ => {
-            vec![]
-        }
-    }
-}
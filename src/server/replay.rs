@@ -0,0 +1,746 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    api::{
+        clock::{Clock, ManualClock},
+        schema::{BorrowedSerialize, Deserialize, Schema, Serialize},
+    },
+    server::{
+        context::{PlayerContext, RoomCommand, RoomContext},
+        hooks::{ActionHooks, Diff, FrameInput, GameHooks, LockstepHooks, RelayHooks, TurnHooks},
+    },
+};
+
+const MAGIC: &[u8; 4] = b"THRR";
+const VERSION: u8 = 3;
+
+// Everything a room needs to run again offline: how it was built and every hook input in order,
+// along with the diffs each of them emitted.
+#[derive(Debug)]
+pub struct Recording {
+    pub type_: String,
+    pub id: String,
+    pub seed: u64,
+    pub options: Vec<u8>,
+    // Wall clock time the room was built at
+    pub started_at: SystemTime,
+    pub records: Vec<Record>,
+}
+
+#[derive(Debug)]
+pub struct Record {
+    // Room clock time since the previous record, or the start for the first one
+    pub elapsed: Duration,
+    pub input: Input,
+    pub diffs: Vec<Diff<Vec<u8>>>,
+}
+
+#[derive(Debug)]
+pub enum Input {
+    Join {
+        player_id: u64,
+        attrs: HashMap<String, String>,
+    },
+    Leave {
+        player_id: u64,
+    },
+    Tick {
+        tick: u64,
+        actions: Vec<(u64, Vec<u8>)>,
+    },
+    Timer {
+        tick: u64,
+        timer_id: u64,
+    },
+    Event {
+        tick: u64,
+        event: Vec<u8>,
+    },
+    Finished,
+    // Action handed to on_action, or buffered by the frame runtimes until its frame is done
+    Action {
+        tick: u64,
+        player_id: u64,
+        action: Vec<u8>,
+    },
+    TurnTimeout {
+        tick: u64,
+        player_id: u64,
+    },
+    Frame {
+        tick: u64,
+        frame: u64,
+    },
+    Relay {
+        tick: u64,
+        server_frame: u64,
+    },
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Corrupted,
+    TypeMismatch,
+    // Recorded by another runtime than the one replayed, e.g. frames replayed as a SyncRuntime.
+    RuntimeMismatch,
+    DeserializationFailure,
+    // Replayed hooks emitted other diffs than the recorded ones.
+    Mismatch { record: usize },
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData => ReplayError::Corrupted,
+            _ => ReplayError::Io(err),
+        }
+    }
+}
+
+impl Recording {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u8(&mut reader)? != VERSION {
+            return Err(ReplayError::Corrupted);
+        }
+
+        let type_ = read_string(&mut reader)?;
+        let id = read_string(&mut reader)?;
+        let seed = read_u64(&mut reader)?;
+        let options = read_bytes(&mut reader)?;
+        let started_at = UNIX_EPOCH + Duration::from_nanos(read_u64(&mut reader)?);
+
+        let mut records = vec![];
+        let mut tag = [0];
+        while reader.read(&mut tag)? != 0 {
+            let input = read_input(&mut reader, tag[0])?;
+            let elapsed = Duration::from_nanos(read_u64(&mut reader)?);
+            let diffs = (0..read_u32(&mut reader)?)
+                .map(|_| read_diff(&mut reader))
+                .collect::<io::Result<_>>()?;
+            records.push(Record {
+                elapsed,
+                input,
+                diffs,
+            });
+        }
+
+        Ok(Self {
+            type_,
+            id,
+            seed,
+            options,
+            started_at,
+            records,
+        })
+    }
+
+    // Replays a room of runtime::sync, or of runtime::action when it only ticks.
+    pub fn replay<H, S>(&self, type_: &'static str) -> Result<(), ReplayError>
+    where
+        H: GameHooks,
+        S: Schema,
+        H::Delta: Serialize<S>,
+        H::Options: for<'a> Deserialize<'a, S>,
+        H::Action: for<'a> Deserialize<'a, S>,
+        H::Event: for<'a> Deserialize<'a, S>,
+    {
+        self.replay_with::<H, S>(type_, |_, _, _, _| Err(ReplayError::RuntimeMismatch))
+    }
+
+    // Replays a room of runtime::action.
+    pub fn replay_actions<H, S>(&self, type_: &'static str) -> Result<(), ReplayError>
+    where
+        H: ActionHooks,
+        S: Schema,
+        H::Delta: Serialize<S>,
+        H::Options: for<'a> Deserialize<'a, S>,
+        H::Action: for<'a> Deserialize<'a, S>,
+        H::Event: for<'a> Deserialize<'a, S>,
+    {
+        self.replay_with::<H, S>(type_, |hooks, room, players_cxts, input| match input {
+            Input::Action {
+                tick,
+                player_id,
+                action,
+            } => {
+                check_tick(room, *tick)?;
+                let action = deserialize::<H::Action, S>(action)?;
+                Ok(hooks.on_action(room, players_cxts, *player_id, action))
+            }
+            _ => Err(ReplayError::RuntimeMismatch),
+        })
+    }
+
+    // Replays a room of runtime::turn, every action advances the room by one tick.
+    pub fn replay_turns<H, S>(&self, type_: &'static str) -> Result<(), ReplayError>
+    where
+        H: TurnHooks,
+        S: Schema,
+        H::Delta: Serialize<S>,
+        H::Options: for<'a> Deserialize<'a, S>,
+        H::Action: for<'a> Deserialize<'a, S>,
+        H::Event: for<'a> Deserialize<'a, S>,
+    {
+        self.replay_with::<H, S>(type_, |hooks, room, players_cxts, input| match input {
+            Input::Action {
+                tick,
+                player_id,
+                action,
+            } => {
+                room.advance();
+                check_tick(room, *tick)?;
+                let action = deserialize::<H::Action, S>(action)?;
+                Ok(hooks.on_action(room, players_cxts, *player_id, action))
+            }
+            Input::TurnTimeout { tick, player_id } => {
+                check_tick(room, *tick)?;
+                Ok(hooks.on_turn_timeout(room, players_cxts, *player_id))
+            }
+            _ => Err(ReplayError::RuntimeMismatch),
+        })
+    }
+
+    // Replays a room of runtime::lockstep, recorded inputs wait for their frame as they did.
+    pub fn replay_lockstep<H, S>(&self, type_: &'static str) -> Result<(), ReplayError>
+    where
+        H: LockstepHooks,
+        S: Schema,
+        H::Delta: Serialize<S>,
+        H::Options: for<'a> Deserialize<'a, S>,
+        H::Action: for<'a> Deserialize<'a, S>,
+        H::Event: for<'a> Deserialize<'a, S>,
+    {
+        let mut inputs: HashMap<u64, BTreeMap<u64, H::Input>> = HashMap::new();
+        self.replay_with::<H, S>(type_, |hooks, room, players_cxts, input| match input {
+            Input::Action {
+                tick,
+                player_id,
+                action,
+            } => {
+                check_tick(room, *tick)?;
+                let (frame, input) = H::frame_input(deserialize::<H::Action, S>(action)?);
+                inputs.entry(frame).or_default().insert(*player_id, input);
+                Ok(None)
+            }
+            Input::Frame { tick, frame } => {
+                room.advance();
+                check_tick(room, *tick)?;
+                let inputs = inputs.remove(frame).unwrap_or_default();
+                Ok(hooks.on_frame(room, players_cxts, *frame, inputs.into_iter().collect()))
+            }
+            _ => Err(ReplayError::RuntimeMismatch),
+        })
+    }
+
+    // Replays a room of runtime::relay, recorded inputs are relayed with the server frame they
+    // were stamped with.
+    pub fn replay_relay<H, S>(&self, type_: &'static str) -> Result<(), ReplayError>
+    where
+        H: RelayHooks,
+        S: Schema,
+        H::Delta: Serialize<S>,
+        H::Options: for<'a> Deserialize<'a, S>,
+        H::Action: for<'a> Deserialize<'a, S>,
+        H::Event: for<'a> Deserialize<'a, S>,
+    {
+        let mut inputs = vec![];
+        self.replay_with::<H, S>(type_, |hooks, room, players_cxts, input| match input {
+            Input::Action {
+                tick,
+                player_id,
+                action,
+            } => {
+                check_tick(room, *tick)?;
+                let (frame, input) = H::frame_input(deserialize::<H::Action, S>(action)?);
+                inputs.push(FrameInput {
+                    player_id: *player_id,
+                    frame,
+                    input,
+                });
+                Ok(None)
+            }
+            Input::Relay { tick, server_frame } => {
+                room.advance();
+                check_tick(room, *tick)?;
+                Ok(hooks.on_relay(
+                    room,
+                    players_cxts,
+                    *server_frame,
+                    std::mem::take(&mut inputs),
+                ))
+            }
+            _ => Err(ReplayError::RuntimeMismatch),
+        })
+    }
+
+    // Builds the hooks from the recorded options and seed, then feeds them every recorded input
+    // checking they emit the same diffs. The room clock is moved by hand to the time each input was
+    // recorded at, so timers fire as they did.
+    // Inputs only some runtimes record are handed to runtime_input.
+    fn replay_with<H, S>(
+        &self,
+        type_: &'static str,
+        mut runtime_input: impl FnMut(
+            &mut H,
+            &mut RoomContext,
+            &HashMap<u64, Arc<PlayerContext>>,
+            &Input,
+        ) -> Result<Option<Vec<Diff<H::Delta>>>, ReplayError>,
+    ) -> Result<(), ReplayError>
+    where
+        H: GameHooks,
+        S: Schema,
+        H::Delta: Serialize<S>,
+        H::Options: for<'a> Deserialize<'a, S>,
+        H::Action: for<'a> Deserialize<'a, S>,
+        H::Event: for<'a> Deserialize<'a, S>,
+    {
+        if self.type_ != type_ {
+            return Err(ReplayError::TypeMismatch);
+        }
+
+        let mut hooks = H::build(deserialize::<H::Options, S>(&self.options)?);
+        let clock = ManualClock::starting_at(self.started_at);
+        let mut room = RoomContext::new(type_, self.id.clone(), self.seed, Arc::new(clock.clone()));
+        let mut players_cxts: HashMap<u64, Arc<PlayerContext>> = HashMap::new();
+        // Timers found expired but not fired yet, the runtime may have fired them a bit later
+        let mut expired = vec![];
+
+        for (index, record) in self.records.iter().enumerate() {
+            clock.advance(record.elapsed);
+            let diffs = match &record.input {
+                Input::Join { player_id, attrs } => {
+                    let cxt = Arc::new(PlayerContext::with_metadata(
+                        *player_id,
+                        Default::default(),
+                        attrs.clone(),
+                    ));
+                    players_cxts.insert(*player_id, Arc::clone(&cxt));
+                    hooks.on_join(&mut room, cxt.as_ref())
+                }
+                Input::Leave { player_id } => players_cxts
+                    .remove(player_id)
+                    .and_then(|cxt| hooks.on_leave(&mut room, cxt.as_ref()))
+                    .map(|diff| vec![diff]),
+                Input::Tick { tick, actions } => {
+                    room.advance();
+                    check_tick(&room, *tick)?;
+                    let actions = actions
+                        .iter()
+                        .map(|(p_id, action)| Ok((*p_id, deserialize::<H::Action, S>(action)?)))
+                        .collect::<Result<_, ReplayError>>()?;
+                    hooks.on_tick(&mut room, &players_cxts, actions)
+                }
+                Input::Timer { timer_id, .. } => {
+                    if !expired.contains(timer_id) {
+                        expired.extend(room.expired_timers());
+                    }
+                    let Some(position) = expired.iter().position(|id| id == timer_id) else {
+                        return Err(ReplayError::Mismatch { record: index });
+                    };
+                    expired.remove(position);
+                    hooks.on_timer(&mut room, &players_cxts, *timer_id)
+                }
+                Input::Event { event, .. } => {
+                    let event = deserialize::<H::Event, S>(event)?;
+                    hooks.on_event(&mut room, &players_cxts, event)
+                }
+                Input::Finished => {
+                    let (is_finished, diff) = hooks.is_finished();
+                    if !is_finished {
+                        return Err(ReplayError::Mismatch { record: index });
+                    }
+                    diff.map(|diff| vec![diff])
+                }
+                input => runtime_input(&mut hooks, &mut room, &players_cxts, input)?,
+            };
+
+            let diffs: Vec<Diff<Vec<u8>>> = diffs
+                .into_iter()
+                .flatten()
+                .map(|diff| diff.map(Serialize::serialize))
+                .collect();
+            if diffs != record.diffs {
+                return Err(ReplayError::Mismatch { record: index });
+            }
+
+            // Mirrors the runtime, kicked and moved players are gone for the next hooks
+            for command in room.take_commands() {
+                match command {
+                    RoomCommand::Kick { player_id, .. } | RoomCommand::Move { player_id, .. } => {
+                        players_cxts.remove(&player_id);
+                    }
+                    RoomCommand::Finish => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn check_tick(room: &RoomContext, tick: u64) -> Result<(), ReplayError> {
+    if room.tick() != tick {
+        return Err(ReplayError::Corrupted);
+    }
+    Ok(())
+}
+
+fn deserialize<T, S>(bytes: &[u8]) -> Result<T, ReplayError>
+where
+    S: Schema,
+    T: for<'a> Deserialize<'a, S>,
+{
+    T::deserialize(bytes).map_err(|_| ReplayError::DeserializationFailure)
+}
+
+// Where rooms write their recordings, one file per room. Options, actions and events are stored
+// serialized with the schema given to new, which should be the server one.
+pub struct RecordSettings<H>
+where
+    H: GameHooks,
+{
+    dir: PathBuf,
+    options: fn(&H::Options) -> Vec<u8>,
+    action: fn(&H::Action) -> Vec<u8>,
+    event: fn(&H::Event) -> Vec<u8>,
+}
+
+impl<H> RecordSettings<H>
+where
+    H: GameHooks,
+{
+    pub fn new<S>(dir: impl Into<PathBuf>) -> Self
+    where
+        S: Schema,
+        H::Options: BorrowedSerialize<S>,
+        H::Action: BorrowedSerialize<S>,
+        H::Event: BorrowedSerialize<S>,
+    {
+        Self {
+            dir: dir.into(),
+            options: |options| BorrowedSerialize::<S>::serialize(options),
+            action: |action| BorrowedSerialize::<S>::serialize(action),
+            event: |event| BorrowedSerialize::<S>::serialize(event),
+        }
+    }
+}
+
+// Written while the room runs, every runtime records the inputs its replay method expects.
+pub(crate) struct Recorder<H>
+where
+    H: GameHooks,
+{
+    writer: BufWriter<File>,
+    diffs: Vec<Diff<Vec<u8>>>,
+    // Room clock time of the previous record
+    last: Instant,
+    action: fn(&H::Action) -> Vec<u8>,
+    event: fn(&H::Event) -> Vec<u8>,
+}
+
+impl<H> Recorder<H>
+where
+    H: GameHooks,
+{
+    // Recorder handed to RoomCore::new, None when the room is not recorded or its file could not
+    // be created. Called along with RoomContext::new so both start at the same time.
+    pub(crate) fn start(
+        settings: Option<&RecordSettings<H>>,
+        type_: &str,
+        id: &str,
+        seed: u64,
+        options: &H::Options,
+        clock: &dyn Clock,
+    ) -> Option<Self> {
+        Self::create(settings?, type_, id, seed, options, clock)
+            .inspect_err(|err| log::error!("Room {type_}:{id} will not be recorded: {err}"))
+            .ok()
+    }
+
+    fn create(
+        settings: &RecordSettings<H>,
+        type_: &str,
+        id: &str,
+        seed: u64,
+        options: &H::Options,
+        clock: &dyn Clock,
+    ) -> io::Result<Self> {
+        let started_at = clock
+            .system_time()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .and_then(|since| u64::try_from(since.as_nanos()).ok())
+            .ok_or(io::ErrorKind::InvalidInput)?;
+
+        std::fs::create_dir_all(&settings.dir)?;
+        let mut writer = BufWriter::new(File::create(Self::path(&settings.dir, type_, id, seed))?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        write_bytes(&mut writer, type_.as_bytes())?;
+        write_bytes(&mut writer, id.as_bytes())?;
+        writer.write_all(&seed.to_le_bytes())?;
+        write_bytes(&mut writer, &(settings.options)(options))?;
+        writer.write_all(&started_at.to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(Self {
+            writer,
+            diffs: vec![],
+            last: clock.now(),
+            action: settings.action,
+            event: settings.event,
+        })
+    }
+
+    // Room ids come from clients, anything but a few safe characters is replaced.
+    fn path(dir: &Path, type_: &str, id: &str, seed: u64) -> PathBuf {
+        let name: String = format!("{type_}-{id}-{seed}")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        dir.join(format!("{name}.rec"))
+    }
+
+    pub(crate) fn diff(&mut self, diff: &Diff<Vec<u8>>) {
+        self.diffs.push(diff.clone());
+    }
+
+    pub(crate) fn action(&self, action: &H::Action) -> Vec<u8> {
+        (self.action)(action)
+    }
+
+    pub(crate) fn event(&self, event: &H::Event) -> Vec<u8> {
+        (self.event)(event)
+    }
+
+    // Written along with the diffs emitted since the previous input, at is the room clock time the
+    // input was handed to the hooks.
+    pub(crate) fn record(&mut self, at: Instant, input: Input) -> io::Result<()> {
+        let elapsed = u64::try_from(at.saturating_duration_since(self.last).as_nanos())
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        self.last = self.last.max(at);
+
+        write_input(&mut self.writer, &input)?;
+        self.writer.write_all(&elapsed.to_le_bytes())?;
+        write_u32(&mut self.writer, self.diffs.len())?;
+        for diff in self.diffs.drain(..) {
+            write_diff(&mut self.writer, &diff)?;
+        }
+        self.writer.flush()
+    }
+}
+
+fn write_input(writer: &mut impl Write, input: &Input) -> io::Result<()> {
+    match input {
+        Input::Join { player_id, attrs } => {
+            writer.write_all(&[0])?;
+            writer.write_all(&player_id.to_le_bytes())?;
+            write_u32(writer, attrs.len())?;
+            for (key, value) in attrs {
+                write_bytes(writer, key.as_bytes())?;
+                write_bytes(writer, value.as_bytes())?;
+            }
+        }
+        Input::Leave { player_id } => {
+            writer.write_all(&[1])?;
+            writer.write_all(&player_id.to_le_bytes())?;
+        }
+        Input::Tick { tick, actions } => {
+            writer.write_all(&[2])?;
+            writer.write_all(&tick.to_le_bytes())?;
+            write_u32(writer, actions.len())?;
+            for (p_id, action) in actions {
+                writer.write_all(&p_id.to_le_bytes())?;
+                write_bytes(writer, action)?;
+            }
+        }
+        Input::Timer { tick, timer_id } => {
+            writer.write_all(&[3])?;
+            writer.write_all(&tick.to_le_bytes())?;
+            writer.write_all(&timer_id.to_le_bytes())?;
+        }
+        Input::Event { tick, event } => {
+            writer.write_all(&[4])?;
+            writer.write_all(&tick.to_le_bytes())?;
+            write_bytes(writer, event)?;
+        }
+        Input::Finished => writer.write_all(&[5])?,
+        Input::Action {
+            tick,
+            player_id,
+            action,
+        } => {
+            writer.write_all(&[6])?;
+            writer.write_all(&tick.to_le_bytes())?;
+            writer.write_all(&player_id.to_le_bytes())?;
+            write_bytes(writer, action)?;
+        }
+        Input::TurnTimeout { tick, player_id } => {
+            writer.write_all(&[7])?;
+            writer.write_all(&tick.to_le_bytes())?;
+            writer.write_all(&player_id.to_le_bytes())?;
+        }
+        Input::Frame { tick, frame } => {
+            writer.write_all(&[8])?;
+            writer.write_all(&tick.to_le_bytes())?;
+            writer.write_all(&frame.to_le_bytes())?;
+        }
+        Input::Relay { tick, server_frame } => {
+            writer.write_all(&[9])?;
+            writer.write_all(&tick.to_le_bytes())?;
+            writer.write_all(&server_frame.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn read_input(reader: &mut impl Read, tag: u8) -> io::Result<Input> {
+    Ok(match tag {
+        0 => {
+            let player_id = read_u64(reader)?;
+            let attrs = (0..read_u32(reader)?)
+                .map(|_| Ok((read_string(reader)?, read_string(reader)?)))
+                .collect::<io::Result<_>>()?;
+            Input::Join { player_id, attrs }
+        }
+        1 => Input::Leave {
+            player_id: read_u64(reader)?,
+        },
+        2 => {
+            let tick = read_u64(reader)?;
+            let actions = (0..read_u32(reader)?)
+                .map(|_| Ok((read_u64(reader)?, read_bytes(reader)?)))
+                .collect::<io::Result<_>>()?;
+            Input::Tick { tick, actions }
+        }
+        3 => Input::Timer {
+            tick: read_u64(reader)?,
+            timer_id: read_u64(reader)?,
+        },
+        4 => Input::Event {
+            tick: read_u64(reader)?,
+            event: read_bytes(reader)?,
+        },
+        5 => Input::Finished,
+        6 => Input::Action {
+            tick: read_u64(reader)?,
+            player_id: read_u64(reader)?,
+            action: read_bytes(reader)?,
+        },
+        7 => Input::TurnTimeout {
+            tick: read_u64(reader)?,
+            player_id: read_u64(reader)?,
+        },
+        8 => Input::Frame {
+            tick: read_u64(reader)?,
+            frame: read_u64(reader)?,
+        },
+        9 => Input::Relay {
+            tick: read_u64(reader)?,
+            server_frame: read_u64(reader)?,
+        },
+        _ => return Err(io::ErrorKind::InvalidData.into()),
+    })
+}
+
+fn write_diff(writer: &mut impl Write, diff: &Diff<Vec<u8>>) -> io::Result<()> {
+    let delta = match diff {
+        Diff::All { delta } => {
+            writer.write_all(&[0])?;
+            delta
+        }
+        Diff::TargetUnique { id, delta } => {
+            writer.write_all(&[1])?;
+            writer.write_all(&id.to_le_bytes())?;
+            delta
+        }
+        Diff::TargetList { ids, delta } => {
+            writer.write_all(&[2])?;
+            write_u32(writer, ids.len())?;
+            for id in ids {
+                writer.write_all(&id.to_le_bytes())?;
+            }
+            delta
+        }
+    };
+    write_bytes(writer, delta)
+}
+
+fn read_diff(reader: &mut impl Read) -> io::Result<Diff<Vec<u8>>> {
+    Ok(match read_u8(reader)? {
+        0 => Diff::All {
+            delta: read_bytes(reader)?,
+        },
+        1 => Diff::TargetUnique {
+            id: read_u64(reader)?,
+            delta: read_bytes(reader)?,
+        },
+        2 => {
+            let ids = (0..read_u32(reader)?)
+                .map(|_| read_u64(reader))
+                .collect::<io::Result<_>>()?;
+            Diff::TargetList {
+                ids,
+                delta: read_bytes(reader)?,
+            }
+        }
+        _ => return Err(io::ErrorKind::InvalidData.into()),
+    })
+}
+
+fn write_u32(writer: &mut impl Write, len: usize) -> io::Result<()> {
+    let len = u32::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    writer.write_all(&len.to_le_bytes())
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    write_u32(writer, bytes.len())?;
+    writer.write_all(bytes)
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; read_u32(reader)? as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    String::from_utf8(read_bytes(reader)?).map_err(|_| io::ErrorKind::InvalidData.into())
+}
@@ -1,26 +1,44 @@
 use std::{
+    any::Any,
     collections::HashMap,
+    mem,
     sync::{Arc, RwLock},
 };
 
+use uuid::Uuid;
+
 use crate::{
     api::{
         error::ThundersError,
         schema::{Deserialize, Schema, Serialize},
     },
-    server::{context::PlayerContext, hooks::GameHooks, protocol::SessionManager},
+    server::{
+        context::PlayerContext, error::ThundersServerError, hooks::GameHooks,
+        protocol::SessionManager,
+    },
 };
 
+pub mod action;
+pub(crate) mod core;
+pub mod lockstep;
+pub mod relay;
 pub mod sync;
+pub mod turn;
 
 #[derive(Debug)]
 pub enum RuntimeAction<H>
 where
     H: GameHooks,
 {
-    Action(H::Action),
+    // Along with the sequence number of the player, if the client sent one.
+    Action(H::Action, Option<u64>),
     Join(Arc<PlayerContext>),
     Leave(u64),
+    Event(H::Event),
+    // Sent on server shutdown, the room notifies its players as finished and stops.
+    Finish,
+    // Sent when a manual clock moved, the room checks its deadlines again.
+    Wake,
 }
 
 pub trait GameRuntime<H, S>
@@ -34,10 +52,12 @@ where
     type Handle: GameHandle<H>;
     type Settings: Send + Sync;
 
+    // Hooks are built by the runtime from the options, which it may record first.
     fn build(
         type_: &'static str,
         id: String,
-        hooks: H,
+        seed: u64,
+        options: H::Options,
         settings: &Self::Settings,
         session_manager: Arc<SessionManager>,
     ) -> Self;
@@ -51,6 +71,11 @@ where
     H: GameHooks,
 {
     fn send(&self, p_id: u64, action: RuntimeAction<H>);
+
+    // Force-finishes the room and waits until its runtime stopped.
+    fn finish(self);
+
+    fn is_finished(&self) -> bool;
 }
 
 // Default async configurable and not with traits
@@ -92,11 +117,67 @@ where
         }
     }
 
-    pub fn register(&self, cxt: Arc<PlayerContext>, room_id: String, options: H::Options) {
+    // Room without players, they join it afterwards. A random seed is picked when none is given.
+    pub fn create(
+        &self,
+        room_id: String,
+        options: H::Options,
+        seed: Option<u64>,
+    ) -> Result<(), ThundersServerError> {
+        let mut handlers = self
+            .handlers
+            .write()
+            .expect("Lock should never be poisoned");
+        if handlers
+            .get(room_id.as_str())
+            .is_some_and(|handler| !handler.is_finished())
+        {
+            return Err(ThundersServerError::RoomAlreadyCreated);
+        }
+
+        let runtime = R::build(
+            self.type_,
+            room_id.clone(),
+            self.seed(room_id.as_str(), seed),
+            options,
+            &self.settings,
+            Arc::clone(&self.session_manager),
+        );
+        handlers.insert(room_id, runtime.start());
+        Ok(())
+    }
+
+    pub fn close(&self, room_id: &str) -> bool {
+        let handler = self
+            .handlers
+            .write()
+            .expect("Lock should never be poisoned")
+            .remove(room_id);
+        handler.map(GameHandle::finish).is_some()
+    }
+
+    pub fn rooms(&self) -> Vec<String> {
+        self.handlers
+            .read()
+            .expect("Lock should never be poisoned")
+            .iter()
+            .filter(|(_, handler)| !handler.is_finished())
+            .map(|(room_id, _)| room_id.clone())
+            .collect()
+    }
+
+    pub fn register(
+        &self,
+        cxt: Arc<PlayerContext>,
+        room_id: String,
+        options: H::Options,
+        seed: Option<u64>,
+    ) {
         let runtime = R::build(
             self.type_,
             room_id.clone(),
-            H::build(options),
+            self.seed(room_id.as_str(), seed),
+            options,
             &self.settings,
             Arc::clone(&self.session_manager),
         );
@@ -107,6 +188,13 @@ where
         }
     }
 
+    // Logged so any room can be replayed afterwards.
+    fn seed(&self, room_id: &str, seed: Option<u64>) -> u64 {
+        let seed = seed.unwrap_or_else(|| Uuid::new_v4().as_u64_pair().0);
+        log::info!("Room {}:{room_id} seeded with {seed}", self.type_);
+        seed
+    }
+
     pub fn join(&self, cxt: Arc<PlayerContext>, room_id: String) {
         if let Ok(handlers) = self.handlers.read() {
             handlers.get(room_id.as_str()).inspect(|handler| {
@@ -123,20 +211,64 @@ where
         }
     }
 
-    pub fn action(&self, cxt: u64, room_id: String, action: H::Action) {
+    pub fn shutdown(&self) {
+        let handlers = mem::take(
+            &mut *self
+                .handlers
+                .write()
+                .expect("Lock should never be poisoned"),
+        );
+        for (_, handler) in handlers {
+            handler.finish();
+        }
+    }
+
+    pub fn event(&self, room_id: &str, event: H::Event) -> Result<(), ThundersServerError> {
+        let handlers = self.handlers.read().expect("Lock should never be poisoned");
+        let handler = handlers
+            .get(room_id)
+            .ok_or(ThundersServerError::RoomNotFound)?;
+        handler.send(0, RuntimeAction::Event(event));
+        Ok(())
+    }
+
+    pub fn action(&self, cxt: u64, room_id: String, action: H::Action, seq: Option<u64>) {
         if let Ok(handlers) = self.handlers.read()
             && let Some(handler) = handlers.get(room_id.as_str())
         {
-            handler.send(cxt, RuntimeAction::Action(action));
+            handler.send(cxt, RuntimeAction::Action(action, seq));
         }
     }
 }
 
 pub trait GameRuntimeAnyHandle: Send + Sync {
-    fn register(&self, cxt: Arc<PlayerContext>, room_id: &str, options: Option<&[u8]>);
+    fn register(
+        &self,
+        cxt: Arc<PlayerContext>,
+        room_id: &str,
+        options: Option<&[u8]>,
+        seed: Option<u64>,
+    );
     fn join(&self, cxt: Arc<PlayerContext>, room_id: &str);
     fn leave(&self, cxt: u64, room_id: String);
-    fn action(&self, cxt: u64, room_id: &str, action: &[u8]) -> Result<(), ThundersError>;
+    fn action(
+        &self,
+        cxt: u64,
+        room_id: &str,
+        action: &[u8],
+        seq: Option<u64>,
+    ) -> Result<(), ThundersError>;
+    fn create(
+        &self,
+        room_id: &str,
+        options: Option<&[u8]>,
+        seed: Option<u64>,
+    ) -> Result<(), ThundersServerError>;
+    fn close(&self, room_id: &str) -> bool;
+    fn contains(&self, room_id: &str) -> bool;
+    fn event(&self, room_id: &str, event: Box<dyn Any + Send>) -> Result<(), ThundersServerError>;
+    fn rooms(&self) -> Vec<String>;
+    fn shutdown(&self);
 }
 
 impl<R, H, S> GameRuntimeAnyHandle for GameRuntimeHandle<R, H, S>
@@ -148,18 +280,24 @@ where
     H::Options: for<'a> Deserialize<'a, S>,
     H::Action: for<'a> Deserialize<'a, S>,
 {
-    fn register(&self, cxt: Arc<PlayerContext>, room_id: &str, options: Option<&[u8]>) {
+    fn register(
+        &self,
+        cxt: Arc<PlayerContext>,
+        room_id: &str,
+        options: Option<&[u8]>,
+        seed: Option<u64>,
+    ) {
         if let Some(options) = options {
             match <H::Options as Deserialize<S>>::deserialize(options) {
                 Ok(options) => {
-                    self.register(cxt, room_id.to_string(), options);
+                    self.register(cxt, room_id.to_string(), options, seed);
                 }
                 Err(err) => {
                     self.session_manager.send(cxt.id(), err);
                 }
             }
         } else {
-            self.register(cxt, room_id.to_string(), H::Options::default());
+            self.register(cxt, room_id.to_string(), H::Options::default(), seed);
         }
     }
 
@@ -171,13 +309,60 @@ where
         self.leave(cxt, room_id);
     }
 
-    fn action(&self, cxt: u64, room_id: &str, action: &[u8]) -> Result<(), ThundersError> {
+    fn action(
+        &self,
+        cxt: u64,
+        room_id: &str,
+        action: &[u8],
+        seq: Option<u64>,
+    ) -> Result<(), ThundersError> {
         match <H::Action as Deserialize<S>>::deserialize(action) {
             Ok(action) => {
-                self.action(cxt, room_id.to_string(), action);
+                self.action(cxt, room_id.to_string(), action, seq);
                 Ok(())
             }
             Err(err) => Err(err),
         }
     }
+
+    fn create(
+        &self,
+        room_id: &str,
+        options: Option<&[u8]>,
+        seed: Option<u64>,
+    ) -> Result<(), ThundersServerError> {
+        let options = match options {
+            Some(options) => <H::Options as Deserialize<S>>::deserialize(options)
+                .map_err(|_| ThundersServerError::DeserializationFailure)?,
+            None => H::Options::default(),
+        };
+        self.create(room_id.to_string(), options, seed)
+    }
+
+    fn close(&self, room_id: &str) -> bool {
+        self.close(room_id)
+    }
+
+    fn contains(&self, room_id: &str) -> bool {
+        self.handlers
+            .read()
+            .expect("Lock should never be poisoned")
+            .get(room_id)
+            .is_some_and(|handler| !handler.is_finished())
+    }
+
+    fn event(&self, room_id: &str, event: Box<dyn Any + Send>) -> Result<(), ThundersServerError> {
+        let event = event
+            .downcast::<H::Event>()
+            .map_err(|_| ThundersServerError::InvalidEvent)?;
+        self.event(room_id, *event)
+    }
+
+    fn rooms(&self) -> Vec<String> {
+        self.rooms()
+    }
+
+    fn shutdown(&self) {
+        self.shutdown();
+    }
 }
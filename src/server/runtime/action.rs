@@ -0,0 +1,191 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    api::{
+        clock::{Clock, SystemClock},
+        message::OutputMessage,
+        schema::{Deserialize, Schema, Serialize},
+    },
+    server::{
+        context::RoomContext,
+        hooks::ActionHooks,
+        protocol::SessionManager,
+        replay::{Input, RecordSettings, Recorder},
+        runtime::{
+            GameRuntime, RuntimeAction,
+            core::{RoomCore, Wake},
+            sync::SyncGameHandle,
+        },
+    },
+};
+
+// Runs ActionHooks, every action is handed to on_action as soon as it arrives and its diffs are
+// sent right away. Rooms only tick when a tick is set, on_tick then runs without actions.
+pub struct ActionRuntime<H>
+where
+    H: ActionHooks,
+{
+    core: RoomCore<H>,
+    tick: Option<Duration>,
+}
+
+pub struct Settings<H>
+where
+    H: ActionHooks,
+{
+    // Tick delays of timers count these ticks, they never fire without it.
+    pub tick_millis: Option<u64>,
+    // Every room writes a recording, see replay::Recording::replay_actions.
+    pub record: Option<RecordSettings<H>>,
+    pub clock: Arc<dyn Clock>,
+}
+
+impl<H> Default for Settings<H>
+where
+    H: ActionHooks,
+{
+    fn default() -> Self {
+        Self {
+            tick_millis: None,
+            record: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl<H> ActionRuntime<H>
+where
+    H: ActionHooks,
+{
+    // Returns true once the room stopped.
+    fn process<S: Schema>(&mut self, (p_id, r_action): (u64, RuntimeAction<H>)) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        match r_action {
+            RuntimeAction::Action(action, seq) => {
+                if let Err(reason) = self.core.hooks.validate_action(p_id, &action) {
+                    self.core.reject(p_id, seq, reason.as_str());
+                    return false;
+                }
+                let core = &mut self.core;
+                let at = core.room.clock().now();
+                core.ack(p_id, seq);
+                let input = core.recorder.as_ref().map(|recorder| Input::Action {
+                    tick: core.room.tick(),
+                    player_id: p_id,
+                    action: recorder.action(&action),
+                });
+                let diffs = core
+                    .hooks
+                    .on_action(&mut core.room, &core.players_cxts, p_id, action);
+                core.notify_all::<S>(diffs);
+                if let Some(input) = input {
+                    core.record(at, input);
+                }
+                core.apply_commands()
+            }
+            RuntimeAction::Leave(id) => self.core.leave::<S>(id),
+            RuntimeAction::Join(cxt) => self.core.join::<S>(cxt),
+            RuntimeAction::Event(event) => self.core.event::<S>(event),
+            RuntimeAction::Finish => {
+                self.core.finish();
+                true
+            }
+            // Handled by RoomCore::wait
+            RuntimeAction::Wake => false,
+        }
+    }
+
+    fn run_tick<S: Schema>(&mut self) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let core = &mut self.core;
+        let at = core.room.clock().now();
+        core.room.advance();
+        let diffs = core
+            .hooks
+            .on_tick(&mut core.room, &core.players_cxts, vec![]);
+        core.notify_all::<S>(diffs);
+        let tick = core.room.tick();
+        core.record(
+            at,
+            Input::Tick {
+                tick,
+                actions: vec![],
+            },
+        );
+        core.apply_commands() || core.fire_timers::<S>()
+    }
+}
+
+impl<H, S> GameRuntime<H, S> for ActionRuntime<H>
+where
+    H: ActionHooks,
+    S: Schema,
+    H::Delta: Serialize<S>,
+    H::Options: for<'a> Deserialize<'a, S>,
+    H::Action: for<'a> Deserialize<'a, S>,
+    for<'a> OutputMessage<'a>: Serialize<S>,
+{
+    type Handle = SyncGameHandle<H>;
+    type Settings = Settings<H>;
+
+    fn build(
+        type_: &'static str,
+        id: String,
+        seed: u64,
+        options: H::Options,
+        settings: &Self::Settings,
+        session_manager: Arc<SessionManager>,
+    ) -> Self {
+        let recorder = Recorder::start(
+            settings.record.as_ref(),
+            type_,
+            id.as_str(),
+            seed,
+            &options,
+            settings.clock.as_ref(),
+        );
+
+        Self {
+            core: RoomCore::new(
+                RoomContext::new(type_, id, seed, Arc::clone(&settings.clock)),
+                H::build(options),
+                session_manager,
+                recorder,
+            ),
+            tick: settings.tick_millis.map(Duration::from_millis),
+        }
+    }
+
+    fn start(mut self) -> Self::Handle {
+        SyncGameHandle::spawn(Arc::clone(self.core.room.clock()), move |action_rx| {
+            let clock = Arc::clone(self.core.room.clock());
+            let mut next_tick: Option<Instant> = self.tick.map(|tick| clock.now() + tick);
+
+            loop {
+                if self.core.check_finished::<S>() {
+                    break;
+                }
+
+                let is_stopped = match self.core.wait::<S>(&action_rx, next_tick) {
+                    Wake::Received(p_id, r_action) => self.process::<S>((p_id, r_action)),
+                    Wake::Deadline => {
+                        next_tick = self.tick.map(|tick| clock.now() + tick);
+                        self.run_tick::<S>()
+                    }
+                    Wake::Timers => false,
+                    Wake::Stopped => true,
+                };
+                if is_stopped {
+                    break;
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    time::Instant,
+};
+
+use crate::{
+    api::{
+        message::{Delivery, OutputMessage},
+        schema::{Schema, Serialize},
+    },
+    server::{
+        context::{PlayerContext, RoomCommand, RoomContext},
+        hooks::{Diff, DiffNotification, GameHooks},
+        protocol::SessionManager,
+        replay::{Input, Recorder},
+        runtime::RuntimeAction,
+    },
+};
+
+// Room state and hook plumbing shared by the runtimes, they only differ in how actions and time
+// drive the hooks.
+pub(crate) struct RoomCore<H>
+where
+    H: GameHooks,
+{
+    pub(crate) room: RoomContext,
+    pub(crate) hooks: H,
+    pub(crate) session_manager: Arc<SessionManager>,
+    pub(crate) players_cxts: HashMap<u64, Arc<PlayerContext>>,
+    pub(crate) recorder: Option<Recorder<H>>,
+    // Last action sequence number processed for each player, sent back with their diffs
+    acks: HashMap<u64, u64>,
+}
+
+// What ended RoomCore::wait.
+pub(crate) enum Wake<H>
+where
+    H: GameHooks,
+{
+    Received(u64, RuntimeAction<H>),
+    // The runtime deadline passed, room timers due at the same time fire on the next wait
+    Deadline,
+    // Room timers due fired, if any
+    Timers,
+    Stopped,
+}
+
+impl<H> RoomCore<H>
+where
+    H: GameHooks,
+{
+    pub(crate) fn new(
+        room: RoomContext,
+        hooks: H,
+        session_manager: Arc<SessionManager>,
+        recorder: Option<Recorder<H>>,
+    ) -> Self {
+        Self {
+            room,
+            hooks,
+            session_manager,
+            players_cxts: HashMap::new(),
+            recorder,
+            acks: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn notify<S: Schema>(&mut self, diff: Diff<H::Delta>)
+    where
+        H::Delta: Serialize<S>,
+    {
+        let delivery = H::delivery(diff.delta());
+        let diff = diff.map(Serialize::serialize);
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.diff(&diff);
+        }
+
+        match diff {
+            Diff::All { delta } => self.send_diff(self.players_cxts.keys(), delta, delivery),
+            Diff::TargetUnique { id, delta } => self.send_diff([id].iter(), delta, delivery),
+            Diff::TargetList { ids, delta } => self.send_diff(ids.iter(), delta, delivery),
+        }
+    }
+
+    fn send_diff<'a>(
+        &self,
+        player_ids: impl Iterator<Item = &'a u64>,
+        delta: Vec<u8>,
+        delivery: Delivery,
+    ) {
+        let mut diff = DiffNotification::new(self.room.type_(), self.room.id(), delta);
+        if self.acks.is_empty() {
+            let player_ids = player_ids.copied().collect::<Vec<_>>();
+            self.session_manager
+                .send_all_with_delivery(player_ids.iter(), &diff, delivery);
+            return;
+        }
+
+        // Acknowledgements differ per player, so is the message
+        for player_id in player_ids {
+            diff.ack = self.acks.get(player_id).copied();
+            self.session_manager
+                .send_with_delivery(*player_id, &diff, delivery);
+        }
+    }
+
+    pub(crate) fn notify_all<S: Schema>(&mut self, diffs: Option<Vec<Diff<H::Delta>>>)
+    where
+        H::Delta: Serialize<S>,
+    {
+        for diff in diffs.into_iter().flatten() {
+            self.notify::<S>(diff);
+        }
+    }
+
+    // Stops recording on failure, the room keeps running. Inputs are stamped with the room clock
+    // before their hooks run, so replays never set timers later than the room did.
+    pub(crate) fn record(&mut self, at: Instant, input: Input) {
+        if let Some(recorder) = self.recorder.as_mut()
+            && let Err(err) = recorder.record(at, input)
+        {
+            log::error!(
+                "Recording of room {}:{} stopped: {err}",
+                self.room.type_(),
+                self.room.id()
+            );
+            self.recorder = None;
+        }
+    }
+
+    // The action is processed, the next diffs of the player acknowledge it.
+    pub(crate) fn ack(&mut self, player_id: u64, seq: Option<u64>) {
+        if let Some(seq) = seq {
+            self.acks.insert(player_id, seq);
+        }
+    }
+
+    // The methods below return true once the room stopped.
+
+    pub(crate) fn join<S: Schema>(&mut self, cxt: Arc<PlayerContext>) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let at = self.room.clock().now();
+        self.players_cxts.insert(cxt.id(), Arc::clone(&cxt));
+        let diffs = self.hooks.on_join(&mut self.room, cxt.as_ref());
+        self.notify_all::<S>(diffs);
+        self.record(
+            at,
+            Input::Join {
+                player_id: cxt.id(),
+                attrs: cxt.attrs().clone(),
+            },
+        );
+        self.apply_commands()
+    }
+
+    pub(crate) fn leave<S: Schema>(&mut self, player_id: u64) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let at = self.room.clock().now();
+        self.acks.remove(&player_id);
+        if let Some(player_context) = self.players_cxts.remove(&player_id) {
+            if let Some(diff) = self.hooks.on_leave(&mut self.room, player_context.as_ref()) {
+                self.notify::<S>(diff);
+            }
+            self.record(at, Input::Leave { player_id });
+        }
+        self.apply_commands()
+    }
+
+    pub(crate) fn event<S: Schema>(&mut self, event: H::Event) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let at = self.room.clock().now();
+        let input = self.recorder.as_ref().map(|recorder| Input::Event {
+            tick: self.room.tick(),
+            event: recorder.event(&event),
+        });
+        let diffs = self
+            .hooks
+            .on_event(&mut self.room, &self.players_cxts, event);
+        self.notify_all::<S>(diffs);
+        if let Some(input) = input {
+            self.record(at, input);
+        }
+        self.apply_commands()
+    }
+
+    pub(crate) fn fire_timers<S: Schema>(&mut self) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let expired = self.room.expired_timers();
+        // Taken after checking the timers so replays find them expired as well
+        let at = self.room.clock().now();
+        for timer_id in expired {
+            let diffs = self
+                .hooks
+                .on_timer(&mut self.room, &self.players_cxts, timer_id);
+            self.notify_all::<S>(diffs);
+            self.record(
+                at,
+                Input::Timer {
+                    tick: self.room.tick(),
+                    timer_id,
+                },
+            );
+            if self.apply_commands() {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Finishes the room once the hooks report it.
+    pub(crate) fn check_finished<S: Schema>(&mut self) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let at = self.room.clock().now();
+        let (is_finished, diff_opt) = self.hooks.is_finished();
+        if !is_finished {
+            return false;
+        }
+        if let Some(diff) = diff_opt {
+            self.notify::<S>(diff);
+        }
+        self.record(at, Input::Finished);
+
+        self.finish();
+        true
+    }
+
+    pub(crate) fn apply_commands(&mut self) -> bool {
+        for command in self.room.take_commands() {
+            match command {
+                RoomCommand::Kick { player_id, reason } => {
+                    self.acks.remove(&player_id);
+                    if self.players_cxts.remove(&player_id).is_some() {
+                        self.session_manager.unsubscribe(
+                            player_id,
+                            self.room.type_(),
+                            self.room.id(),
+                        );
+                        self.session_manager.send(
+                            player_id,
+                            OutputMessage::Kick {
+                                type_: self.room.type_(),
+                                id: self.room.id(),
+                                reason: reason.as_str().into(),
+                            },
+                        );
+                    }
+                }
+                RoomCommand::Move {
+                    player_id,
+                    type_,
+                    id,
+                } => {
+                    self.acks.remove(&player_id);
+                    let Some(player_cxt) = self.players_cxts.remove(&player_id) else {
+                        continue;
+                    };
+                    self.session_manager
+                        .unsubscribe(player_id, self.room.type_(), self.room.id());
+                    self.session_manager.send(
+                        player_id,
+                        OutputMessage::Move {
+                            type_: self.room.type_(),
+                            id: self.room.id(),
+                            to_type: type_.as_str(),
+                            to_id: id.as_str(),
+                        },
+                    );
+                    if !self
+                        .session_manager
+                        .join(player_cxt, type_.as_str(), id.as_str())
+                    {
+                        log::warn!("Player {player_id} could not be moved to room {type_}:{id}");
+                    }
+                }
+                RoomCommand::Finish => {
+                    self.finish();
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Sleeps until a message, the runtime deadline or the next room timer. Without any deadline
+    // nothing can happen before the next message.
+    pub(crate) fn wait<S: Schema>(
+        &mut self,
+        action_rx: &Receiver<(u64, RuntimeAction<H>)>,
+        deadline: Option<Instant>,
+    ) -> Wake<H>
+    where
+        H::Delta: Serialize<S>,
+    {
+        let clock = Arc::clone(self.room.clock());
+        let next_deadline = match (deadline, self.room.next_deadline()) {
+            (Some(deadline), Some(timer)) => Some(deadline.min(timer)),
+            (deadline, timer) => deadline.or(timer),
+        };
+        let received = match next_deadline {
+            Some(next_deadline) => action_rx.recv_timeout(clock.block_for(next_deadline)),
+            None => action_rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            // Same as a timeout, the clock may have reached a deadline
+            Ok((_, RuntimeAction::Wake)) | Err(RecvTimeoutError::Timeout) => {
+                if deadline.is_some_and(|deadline| clock.now() >= deadline) {
+                    Wake::Deadline
+                } else if self.fire_timers::<S>() {
+                    Wake::Stopped
+                } else {
+                    Wake::Timers
+                }
+            }
+            Ok((p_id, r_action)) => Wake::Received(p_id, r_action),
+            // Handle dropped without finishing the room, e.g. the server task was dropped
+            Err(RecvTimeoutError::Disconnected) => {
+                self.finish();
+                Wake::Stopped
+            }
+        }
+    }
+
+    // Rejected actions are not acknowledged, the client drops them through the sequence number.
+    pub(crate) fn reject(&self, player_id: u64, seq: Option<u64>, reason: &str) {
+        self.session_manager.send(
+            player_id,
+            OutputMessage::Reject {
+                type_: self.room.type_(),
+                id: self.room.id(),
+                reason: reason.into(),
+                seq,
+            },
+        );
+    }
+
+    pub(crate) fn finish(&self) {
+        let diff = DiffNotification::finish(self.room.type_(), self.room.id());
+        self.session_manager
+            .send_all(self.players_cxts.keys(), &diff);
+        self.session_manager
+            .unsubscribe_room(self.room.type_(), self.room.id());
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    api::{
+        clock::{Clock, SystemClock},
+        message::OutputMessage,
+        schema::{Deserialize, Schema, Serialize},
+    },
+    server::{
+        context::RoomContext,
+        hooks::LockstepHooks,
+        protocol::SessionManager,
+        replay::{Input, RecordSettings, Recorder},
+        runtime::{
+            GameRuntime, RuntimeAction,
+            core::{RoomCore, Wake},
+            sync::SyncGameHandle,
+        },
+    },
+};
+
+// Runs LockstepHooks. A frame is done once every player sent its input, or frame_timeout after
+// the first input of the frame arrived, then on_frame broadcasts it. Every frame advances the room
+// by one tick.
+pub struct LockstepRuntime<H>
+where
+    H: LockstepHooks,
+{
+    core: RoomCore<H>,
+    frame_timeout: Duration,
+    input_delay: u64,
+    // Next frame to be done
+    frame: u64,
+    frame_deadline: Option<Instant>,
+    inputs: HashMap<u64, BTreeMap<u64, H::Input>>,
+}
+
+pub struct Settings<H>
+where
+    H: LockstepHooks,
+{
+    // How long stragglers are waited for.
+    pub frame_timeout_millis: u64,
+    // Frames between an input and the frame it is meant for, must match client::lockstep. The
+    // first frames have no inputs and inputs further ahead are rejected.
+    pub input_delay: u64,
+    // Every room writes a recording, see replay::Recording::replay_lockstep.
+    pub record: Option<RecordSettings<H>>,
+    pub clock: Arc<dyn Clock>,
+}
+
+impl<H> Default for Settings<H>
+where
+    H: LockstepHooks,
+{
+    fn default() -> Self {
+        Self {
+            frame_timeout_millis: 100,
+            input_delay: 2,
+            record: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl<H> LockstepRuntime<H>
+where
+    H: LockstepHooks,
+{
+    // Returns true once the room stopped.
+    fn process<S: Schema>(&mut self, (p_id, r_action): (u64, RuntimeAction<H>)) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        match r_action {
+            RuntimeAction::Action(action, seq) => {
+                let at = self.core.room.clock().now();
+                let recorded = self
+                    .core
+                    .recorder
+                    .as_ref()
+                    .map(|recorder| recorder.action(&action));
+                let (frame, input) = H::frame_input(action);
+                if frame < self.frame {
+                    self.core
+                        .reject(p_id, seq, format!("Late input for frame {frame}").as_str());
+                    return false;
+                }
+                if frame > self.frame + self.input_delay {
+                    self.core
+                        .reject(p_id, seq, format!("Early input for frame {frame}").as_str());
+                    return false;
+                }
+                self.core.ack(p_id, seq);
+                if let Some(action) = recorded {
+                    let tick = self.core.room.tick();
+                    self.core.record(
+                        at,
+                        Input::Action {
+                            tick,
+                            player_id: p_id,
+                            action,
+                        },
+                    );
+                }
+
+                self.inputs.entry(frame).or_default().insert(p_id, input);
+                if frame == self.frame && self.frame_deadline.is_none() {
+                    self.frame_deadline = Some(self.core.room.clock().now() + self.frame_timeout);
+                }
+                self.run_done_frames::<S>()
+            }
+            // Left players are not waited for anymore
+            RuntimeAction::Leave(id) => self.core.leave::<S>(id) || self.run_done_frames::<S>(),
+            RuntimeAction::Join(cxt) => self.core.join::<S>(cxt),
+            RuntimeAction::Event(event) => self.core.event::<S>(event),
+            RuntimeAction::Finish => {
+                self.core.finish();
+                true
+            }
+            // Handled by RoomCore::wait
+            RuntimeAction::Wake => false,
+        }
+    }
+
+    fn run_done_frames<S: Schema>(&mut self) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        while self.inputs.get(&self.frame).is_some_and(|inputs| {
+            self.core
+                .players_cxts
+                .keys()
+                .all(|p_id| inputs.contains_key(p_id))
+        }) {
+            if self.run_frame::<S>() {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn run_frame<S: Schema>(&mut self) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let frame = self.frame;
+        let inputs = self
+            .inputs
+            .remove(&frame)
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<_>>();
+        self.frame += 1;
+        self.frame_deadline = self
+            .inputs
+            .contains_key(&self.frame)
+            .then(|| self.core.room.clock().now() + self.frame_timeout);
+
+        let core = &mut self.core;
+        let at = core.room.clock().now();
+        core.room.advance();
+        let diffs = core
+            .hooks
+            .on_frame(&mut core.room, &core.players_cxts, frame, inputs);
+        core.notify_all::<S>(diffs);
+        let tick = core.room.tick();
+        core.record(at, Input::Frame { tick, frame });
+        core.apply_commands() || core.fire_timers::<S>()
+    }
+}
+
+impl<H, S> GameRuntime<H, S> for LockstepRuntime<H>
+where
+    H: LockstepHooks,
+    S: Schema,
+    H::Delta: Serialize<S>,
+    H::Options: for<'a> Deserialize<'a, S>,
+    H::Action: for<'a> Deserialize<'a, S>,
+    for<'a> OutputMessage<'a>: Serialize<S>,
+{
+    type Handle = SyncGameHandle<H>;
+    type Settings = Settings<H>;
+
+    fn build(
+        type_: &'static str,
+        id: String,
+        seed: u64,
+        options: H::Options,
+        settings: &Self::Settings,
+        session_manager: Arc<SessionManager>,
+    ) -> Self {
+        let recorder = Recorder::start(
+            settings.record.as_ref(),
+            type_,
+            id.as_str(),
+            seed,
+            &options,
+            settings.clock.as_ref(),
+        );
+
+        Self {
+            core: RoomCore::new(
+                RoomContext::new(type_, id, seed, Arc::clone(&settings.clock)),
+                H::build(options),
+                session_manager,
+                recorder,
+            ),
+            frame_timeout: Duration::from_millis(settings.frame_timeout_millis),
+            input_delay: settings.input_delay,
+            frame: settings.input_delay,
+            frame_deadline: None,
+            inputs: HashMap::new(),
+        }
+    }
+
+    fn start(mut self) -> Self::Handle {
+        SyncGameHandle::spawn(Arc::clone(self.core.room.clock()), move |action_rx| {
+            loop {
+                if self.core.check_finished::<S>() {
+                    break;
+                }
+
+                let is_stopped = match self.core.wait::<S>(&action_rx, self.frame_deadline) {
+                    Wake::Received(p_id, r_action) => self.process::<S>((p_id, r_action)),
+                    Wake::Deadline => {
+                        log::debug!(
+                            "Frame {} of room {}:{} done without stragglers",
+                            self.frame,
+                            self.core.room.type_(),
+                            self.core.room.id()
+                        );
+                        self.run_frame::<S>() || self.run_done_frames::<S>()
+                    }
+                    Wake::Timers => false,
+                    Wake::Stopped => true,
+                };
+                if is_stopped {
+                    break;
+                }
+            }
+        })
+    }
+}
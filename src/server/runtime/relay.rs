@@ -0,0 +1,221 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    api::{
+        clock::{Clock, SystemClock},
+        message::OutputMessage,
+        schema::{Deserialize, Schema, Serialize},
+    },
+    server::{
+        context::RoomContext,
+        hooks::{FrameInput, RelayHooks},
+        protocol::SessionManager,
+        replay::{Input, RecordSettings, Recorder},
+        runtime::{
+            GameRuntime, RuntimeAction,
+            core::{RoomCore, Wake},
+            sync::SyncGameHandle,
+        },
+    },
+};
+
+// Runs RelayHooks. Inputs are stamped with the server frame they arrived in and handed to
+// on_relay once that frame ends, the room sleeps while no input comes. Every relayed frame
+// advances the room by one tick.
+pub struct RelayRuntime<H>
+where
+    H: RelayHooks,
+{
+    core: RoomCore<H>,
+    frame: Duration,
+    started_at: Instant,
+    // Server frame the buffered inputs arrived in
+    server_frame: u64,
+    inputs: Vec<FrameInput<H::Input>>,
+}
+
+pub struct Settings<H>
+where
+    H: RelayHooks,
+{
+    pub frame_millis: u64,
+    // Every room writes a recording, see replay::Recording::replay_relay.
+    pub record: Option<RecordSettings<H>>,
+    pub clock: Arc<dyn Clock>,
+}
+
+impl<H> Default for Settings<H>
+where
+    H: RelayHooks,
+{
+    fn default() -> Self {
+        Self {
+            frame_millis: 16,
+            record: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl<H> RelayRuntime<H>
+where
+    H: RelayHooks,
+{
+    // Frames since the room started.
+    fn current_frame(&self) -> u64 {
+        let elapsed = self.core.room.clock().now() - self.started_at;
+        (elapsed.as_nanos() / self.frame.as_nanos().max(1)) as u64
+    }
+
+    // End of the server frame of the buffered inputs, if any. None as well once it no longer fits
+    // an Instant, hundreds of years away.
+    fn relay_deadline(&self) -> Option<Instant> {
+        if self.inputs.is_empty() {
+            return None;
+        }
+        let nanos = self.frame.as_nanos() * u128::from(self.server_frame + 1);
+        self.started_at
+            .checked_add(Duration::from_nanos(u64::try_from(nanos).ok()?))
+    }
+
+    // Returns true once the room stopped.
+    fn process<S: Schema>(&mut self, (p_id, r_action): (u64, RuntimeAction<H>)) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        match r_action {
+            RuntimeAction::Action(action, seq) => {
+                // Inputs of a frame already over are relayed first
+                if self
+                    .relay_deadline()
+                    .is_some_and(|deadline| self.core.room.clock().now() >= deadline)
+                    && self.relay::<S>()
+                {
+                    return true;
+                }
+                if self.inputs.is_empty() {
+                    self.server_frame = self.current_frame();
+                }
+                self.core.ack(p_id, seq);
+                let at = self.core.room.clock().now();
+                let input = self.core.recorder.as_ref().map(|recorder| Input::Action {
+                    tick: self.core.room.tick(),
+                    player_id: p_id,
+                    action: recorder.action(&action),
+                });
+                if let Some(input) = input {
+                    self.core.record(at, input);
+                }
+                let (frame, input) = H::frame_input(action);
+                self.inputs.push(FrameInput {
+                    player_id: p_id,
+                    frame,
+                    input,
+                });
+                false
+            }
+            RuntimeAction::Leave(id) => self.core.leave::<S>(id),
+            RuntimeAction::Join(cxt) => self.core.join::<S>(cxt),
+            RuntimeAction::Event(event) => self.core.event::<S>(event),
+            RuntimeAction::Finish => {
+                self.core.finish();
+                true
+            }
+            // Handled by RoomCore::wait
+            RuntimeAction::Wake => false,
+        }
+    }
+
+    fn relay<S: Schema>(&mut self) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let inputs = std::mem::take(&mut self.inputs);
+        let core = &mut self.core;
+        let at = core.room.clock().now();
+        core.room.advance();
+        let diffs = core.hooks.on_relay(
+            &mut core.room,
+            &core.players_cxts,
+            self.server_frame,
+            inputs,
+        );
+        core.notify_all::<S>(diffs);
+        let tick = core.room.tick();
+        core.record(
+            at,
+            Input::Relay {
+                tick,
+                server_frame: self.server_frame,
+            },
+        );
+        core.apply_commands() || core.fire_timers::<S>()
+    }
+}
+
+impl<H, S> GameRuntime<H, S> for RelayRuntime<H>
+where
+    H: RelayHooks,
+    S: Schema,
+    H::Delta: Serialize<S>,
+    H::Options: for<'a> Deserialize<'a, S>,
+    H::Action: for<'a> Deserialize<'a, S>,
+    for<'a> OutputMessage<'a>: Serialize<S>,
+{
+    type Handle = SyncGameHandle<H>;
+    type Settings = Settings<H>;
+
+    fn build(
+        type_: &'static str,
+        id: String,
+        seed: u64,
+        options: H::Options,
+        settings: &Self::Settings,
+        session_manager: Arc<SessionManager>,
+    ) -> Self {
+        let recorder = Recorder::start(
+            settings.record.as_ref(),
+            type_,
+            id.as_str(),
+            seed,
+            &options,
+            settings.clock.as_ref(),
+        );
+
+        Self {
+            core: RoomCore::new(
+                RoomContext::new(type_, id, seed, Arc::clone(&settings.clock)),
+                H::build(options),
+                session_manager,
+                recorder,
+            ),
+            frame: Duration::from_millis(settings.frame_millis),
+            started_at: settings.clock.now(),
+            server_frame: 0,
+            inputs: vec![],
+        }
+    }
+
+    fn start(mut self) -> Self::Handle {
+        SyncGameHandle::spawn(Arc::clone(self.core.room.clock()), move |action_rx| {
+            loop {
+                if self.core.check_finished::<S>() {
+                    break;
+                }
+
+                let is_stopped = match self.core.wait::<S>(&action_rx, self.relay_deadline()) {
+                    Wake::Received(p_id, r_action) => self.process::<S>((p_id, r_action)),
+                    Wake::Deadline => self.relay::<S>(),
+                    Wake::Timers => false,
+                    Wake::Stopped => true,
+                };
+                if is_stopped {
+                    break;
+                }
+            }
+        })
+    }
+}
@@ -1,21 +1,25 @@
 use std::{
-    collections::HashMap,
     mem,
     sync::{Arc, mpsc},
     thread::{self, JoinHandle},
-    time::{Duration, Instant},
+    time::Duration,
 };
 
 use crate::{
     api::{
+        clock::{Clock, SystemClock},
         message::OutputMessage,
         schema::{Deserialize, Schema, Serialize},
     },
     server::{
-        context::PlayerContext,
-        hooks::{Diff, DiffNotification, GameHooks},
+        context::RoomContext,
+        hooks::GameHooks,
         protocol::SessionManager,
-        runtime::{GameHandle, GameRuntime, RuntimeAction},
+        replay::{Input, RecordSettings, Recorder},
+        runtime::{
+            GameHandle, GameRuntime, RuntimeAction,
+            core::{RoomCore, Wake},
+        },
     },
 };
 
@@ -23,43 +27,96 @@ pub struct SyncRuntime<H>
 where
     H: GameHooks,
 {
-    type_: &'static str,
-    id: String,
-    hooks: H,
+    core: RoomCore<H>,
     tick_no_action: Duration,
     tick: Duration,
-    session_manager: Arc<SessionManager>,
-    players_cxts: HashMap<u64, Arc<PlayerContext>>,
 }
 
-pub struct Settings {
+pub struct Settings<H>
+where
+    H: GameHooks,
+{
     pub tick_no_action_millis: u64,
     pub tick_millis: u64,
+    // Every room writes a recording, see replay::Recording.
+    pub record: Option<RecordSettings<H>>,
+    pub clock: Arc<dyn Clock>,
+}
+
+impl<H> Default for Settings<H>
+where
+    H: GameHooks,
+{
+    fn default() -> Self {
+        Self {
+            tick_no_action_millis: 1000,
+            tick_millis: 16,
+            record: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
 }
 
 impl<H> SyncRuntime<H>
 where
     H: GameHooks,
 {
-    fn notify<S: Schema>(&self, diff: Diff<H::Delta>)
+    // Returns true once the room stopped.
+    fn process<S: Schema>(
+        &mut self,
+        (p_id, r_action): (u64, RuntimeAction<H>),
+        actions_buffer: &mut Vec<(u64, H::Action, Option<u64>)>,
+    ) -> bool
     where
         H::Delta: Serialize<S>,
     {
-        match diff {
-            Diff::All { delta } => {
-                let diff = DiffNotification::new(self.type_, self.id.as_str(), delta.serialize());
-                self.session_manager
-                    .send_all(self.players_cxts.keys(), &diff);
-            }
-            Diff::TargetUnique { id, delta } => {
-                let diff = DiffNotification::new(self.type_, self.id.as_str(), delta.serialize());
-                self.session_manager.send(id, &diff);
+        match r_action {
+            RuntimeAction::Action(action, seq) => {
+                actions_buffer.push((p_id, action, seq));
+                false
             }
-            Diff::TargetList { ids, delta } => {
-                let diff = DiffNotification::new(self.type_, self.id.as_str(), delta.serialize());
-                self.session_manager.send_all(ids.iter(), &diff);
+            RuntimeAction::Leave(id) => self.core.leave::<S>(id),
+            RuntimeAction::Join(cxt) => self.core.join::<S>(cxt),
+            RuntimeAction::Event(event) => self.core.event::<S>(event),
+            RuntimeAction::Finish => {
+                self.core.finish();
+                true
             }
+            // Handled by RoomCore::wait
+            RuntimeAction::Wake => false,
+        }
+    }
+
+    fn run_tick<S: Schema>(&mut self, actions: Vec<(u64, H::Action, Option<u64>)>) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        let core = &mut self.core;
+        let at = core.room.clock().now();
+        core.room.advance();
+        // Acknowledged by the diffs of this tick
+        let actions = actions
+            .into_iter()
+            .map(|(p_id, action, seq)| {
+                core.ack(p_id, seq);
+                (p_id, action)
+            })
+            .collect::<Vec<_>>();
+        let input = core.recorder.as_ref().map(|recorder| Input::Tick {
+            tick: core.room.tick(),
+            actions: actions
+                .iter()
+                .map(|(p_id, action)| (*p_id, recorder.action(action)))
+                .collect(),
+        });
+        let diffs = core
+            .hooks
+            .on_tick(&mut core.room, &core.players_cxts, actions);
+        core.notify_all::<S>(diffs);
+        if let Some(input) = input {
+            core.record(at, input);
         }
+        core.apply_commands() || core.fire_timers::<S>()
     }
 }
 
@@ -73,127 +130,76 @@ where
     for<'a> OutputMessage<'a>: Serialize<S>,
 {
     type Handle = SyncGameHandle<H>;
-    type Settings = Settings;
+    type Settings = Settings<H>;
 
     fn build(
         type_: &'static str,
         id: String,
-        hooks: H,
+        seed: u64,
+        options: H::Options,
         settings: &Self::Settings,
         session_manager: Arc<SessionManager>,
     ) -> Self {
-        Self {
-            id,
+        let recorder = Recorder::start(
+            settings.record.as_ref(),
             type_,
-            hooks,
+            id.as_str(),
+            seed,
+            &options,
+            settings.clock.as_ref(),
+        );
+
+        Self {
+            core: RoomCore::new(
+                RoomContext::new(type_, id, seed, Arc::clone(&settings.clock)),
+                H::build(options),
+                session_manager,
+                recorder,
+            ),
             tick_no_action: Duration::from_millis(settings.tick_no_action_millis),
             tick: Duration::from_millis(settings.tick_millis),
-            session_manager,
-            players_cxts: Default::default(),
         }
     }
 
     fn start(mut self) -> Self::Handle {
-        let (action_tx, action_rx) = mpsc::channel::<(u64, RuntimeAction<H>)>();
-        let r_handle = thread::spawn(move || {
+        SyncGameHandle::spawn(Arc::clone(self.core.room.clock()), move |action_rx| {
+            let clock = Arc::clone(self.core.room.clock());
             let mut actions_buffer = Vec::new();
-            let mut now;
-            let mut tick;
+            let mut idle_since = clock.now();
+            // Actions sent within a tick are handed together to on_tick
+            let mut tick_deadline = None;
 
             loop {
-                let (is_finished, diff_opt) = self.hooks.is_finished();
-                if is_finished {
-                    if let Some(diff) = diff_opt {
-                        self.notify::<S>(diff);
-                    }
-
-                    let diff = DiffNotification::finish(self.type_, self.id.as_str());
-                    self.session_manager
-                        .send_all(self.players_cxts.keys(), &diff);
+                if self.core.check_finished::<S>() {
                     break;
                 }
-                if let Ok(event) = action_rx.recv_timeout(self.tick_no_action) {
-                    match event.1 {
-                        RuntimeAction::Action(action) => {
-                            actions_buffer.push((event.0, action));
-                            now = Instant::now();
-                            tick = self.tick;
-                        }
-
-                        RuntimeAction::Leave(id) => {
-                            if let Some(player_context) = self.players_cxts.remove(&id)
-                                && let Some(diff) = self.hooks.on_leave(player_context.as_ref())
-                            {
-                                self.notify::<S>(diff);
-                            }
 
-                            continue;
-                        }
-
-                        RuntimeAction::Join(cxt) => {
-                            self.players_cxts.insert(cxt.id(), Arc::clone(&cxt));
-                            if let Some(diffs) = self.hooks.on_join(cxt.as_ref()) {
-                                for diff in diffs {
-                                    self.notify::<S>(diff);
-                                }
+                // Idle rooms sleep until the idle tick, or the next timer when it comes before
+                let deadline = tick_deadline.unwrap_or(idle_since + self.tick_no_action);
+                let is_stopped = match self.core.wait::<S>(&action_rx, Some(deadline)) {
+                    Wake::Received(p_id, r_action) => {
+                        if tick_deadline.is_none() {
+                            idle_since = clock.now();
+                            if matches!(r_action, RuntimeAction::Action(..)) {
+                                tick_deadline = Some(idle_since + self.tick);
                             }
-                            continue;
                         }
+                        self.process::<S>((p_id, r_action), &mut actions_buffer)
                     }
-                } else {
-                    if let Some(diffs) = self.hooks.on_tick(&self.players_cxts, vec![]) {
-                        for diff in diffs {
-                            self.notify::<S>(diff);
-                        }
-                    }
-                    continue;
-                }
-
-                while let Ok(event) = action_rx.recv_timeout(tick) {
-                    match event.1 {
-                        RuntimeAction::Action(action) => {
-                            actions_buffer.push((event.0, action));
-                        }
-                        RuntimeAction::Leave(id) => {
-                            if let Some(player_context) = self.players_cxts.remove(&id)
-                                && let Some(diff) = self.hooks.on_leave(player_context.as_ref())
-                            {
-                                self.notify::<S>(diff);
-                            }
-                        }
-
-                        RuntimeAction::Join(cxt) => {
-                            self.players_cxts.insert(cxt.id(), Arc::clone(&cxt));
-                            if let Some(diffs) = self.hooks.on_join(cxt.as_ref()) {
-                                for diff in diffs {
-                                    self.notify::<S>(diff);
-                                }
-                            }
+                    Wake::Deadline => {
+                        if tick_deadline.take().is_none() {
+                            idle_since = clock.now();
                         }
+                        self.run_tick::<S>(mem::take(&mut actions_buffer))
                     }
-
-                    if let Some(new_tick) = tick.checked_sub(now.elapsed()) {
-                        tick = new_tick;
-                    } else {
-                        break;
-                    }
-                }
-
-                if let Some(diffs) = self
-                    .hooks
-                    .on_tick(&self.players_cxts, mem::take(&mut actions_buffer))
-                {
-                    for diff in diffs {
-                        self.notify::<S>(diff);
-                    }
+                    Wake::Timers => false,
+                    Wake::Stopped => true,
+                };
+                if is_stopped {
+                    break;
                 }
             }
-        });
-
-        SyncGameHandle {
-            action_tx,
-            _r_handle: r_handle,
-        }
+        })
     }
 }
 
@@ -202,7 +208,35 @@ where
     H: GameHooks,
 {
     action_tx: mpsc::Sender<(u64, RuntimeAction<H>)>,
-    _r_handle: JoinHandle<()>,
+    r_handle: JoinHandle<()>,
+    // The clock only keeps a weak reference, it stops calling it once the handle is dropped
+    _waker: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl<H> SyncGameHandle<H>
+where
+    H: GameHooks,
+{
+    // Runs the room loop on its own thread, shared by the thread based runtimes. The loop is woken
+    // up whenever the clock is moved by hand.
+    pub(crate) fn spawn<F>(clock: Arc<dyn Clock>, run: F) -> Self
+    where
+        F: FnOnce(mpsc::Receiver<(u64, RuntimeAction<H>)>) + Send + 'static,
+    {
+        let (action_tx, action_rx) = mpsc::channel::<(u64, RuntimeAction<H>)>();
+        let waker_tx = action_tx.clone();
+        let waker: Arc<dyn Fn() + Send + Sync> = Arc::new(move || {
+            let _ = waker_tx.send((0, RuntimeAction::Wake));
+        });
+        clock.subscribe(Arc::downgrade(&waker));
+
+        let r_handle = thread::spawn(move || run(action_rx));
+        Self {
+            action_tx,
+            r_handle,
+            _waker: waker,
+        }
+    }
 }
 
 impl<H> GameHandle<H> for SyncGameHandle<H>
@@ -211,8 +245,8 @@ where
 {
     fn send(&self, p_id: u64, r_action: RuntimeAction<H>) {
         match &r_action {
-            RuntimeAction::Action(action) => {
-                log::trace!("SERVER received action request. Action: {action:?} ");
+            RuntimeAction::Action(action, seq) => {
+                log::trace!("SERVER received action request. Action: {action:?}, Seq: {seq:?} ");
             }
             RuntimeAction::Join(cxt) => {
                 log::trace!("SERVER received join request. PlayerContext: {cxt:?} ");
@@ -221,10 +255,34 @@ where
             RuntimeAction::Leave(id) => {
                 log::trace!("SERVER received leave request. PlayerId: {id} ");
             }
+
+            RuntimeAction::Event(event) => {
+                log::trace!("SERVER received event. Event: {event:?} ");
+            }
+
+            RuntimeAction::Finish => {
+                log::trace!("SERVER received finish request.");
+            }
+
+            RuntimeAction::Wake => {
+                log::trace!("SERVER received clock wake up.");
+            }
         }
 
         if self.action_tx.send((p_id, r_action)).is_err() {
             log::warn!("Game runtime stopped, skipping action.");
         }
     }
+
+    fn is_finished(&self) -> bool {
+        self.r_handle.is_finished()
+    }
+
+    fn finish(self) {
+        // Rooms already finished by themselves stopped listening
+        let _ = self.action_tx.send((0, RuntimeAction::Finish));
+        if self.r_handle.join().is_err() {
+            log::error!("Game runtime panicked before finishing.");
+        }
+    }
 }
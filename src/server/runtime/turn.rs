@@ -0,0 +1,203 @@
+use std::{sync::Arc, time::Instant};
+
+use crate::{
+    api::{
+        clock::{Clock, SystemClock},
+        message::OutputMessage,
+        schema::{Deserialize, Schema, Serialize},
+    },
+    server::{
+        context::RoomContext,
+        hooks::TurnHooks,
+        protocol::SessionManager,
+        replay::{Input, RecordSettings, Recorder},
+        runtime::{
+            GameRuntime, RuntimeAction,
+            core::{RoomCore, Wake},
+            sync::SyncGameHandle,
+        },
+    },
+};
+
+// Runs TurnHooks without ticks, the room thread sleeps until an action, a timer or the turn time
+// limit. Every accepted action advances the room by one tick, so tick delays count moves.
+pub struct TurnRuntime<H>
+where
+    H: TurnHooks,
+{
+    core: RoomCore<H>,
+    // Player holding the turn and when it runs out
+    turn: Option<(u64, Option<Instant>)>,
+}
+
+pub struct Settings<H>
+where
+    H: TurnHooks,
+{
+    // Every room writes a recording, see replay::Recording::replay_turns.
+    pub record: Option<RecordSettings<H>>,
+    pub clock: Arc<dyn Clock>,
+}
+
+impl<H> Default for Settings<H>
+where
+    H: TurnHooks,
+{
+    fn default() -> Self {
+        Self {
+            record: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl<H> TurnRuntime<H>
+where
+    H: TurnHooks,
+{
+    // Restarts the time limit when the turn changed hands.
+    fn update_turn(&mut self) {
+        let current = self.core.hooks.current_turn();
+        if current != self.turn.map(|(player_id, _)| player_id) {
+            let now = self.core.room.clock().now();
+            self.turn = current.map(|player_id| {
+                let limit = self.core.hooks.turn_time_limit();
+                (player_id, limit.map(|limit| now + limit))
+            });
+        }
+    }
+
+    // Returns true once the room stopped.
+    fn process<S: Schema>(&mut self, (p_id, r_action): (u64, RuntimeAction<H>)) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        match r_action {
+            RuntimeAction::Action(action, seq) => {
+                if self.core.hooks.current_turn() != Some(p_id) {
+                    log::debug!("Rejected action of player {p_id} out of turn");
+                    self.core.reject(p_id, seq, "Not your turn");
+                    return false;
+                }
+                if let Err(reason) = self.core.hooks.validate_action(p_id, &action) {
+                    self.core.reject(p_id, seq, reason.as_str());
+                    return false;
+                }
+                self.core.ack(p_id, seq);
+
+                let core = &mut self.core;
+                let at = core.room.clock().now();
+                core.room.advance();
+                let input = core.recorder.as_ref().map(|recorder| Input::Action {
+                    tick: core.room.tick(),
+                    player_id: p_id,
+                    action: recorder.action(&action),
+                });
+                let diffs = core
+                    .hooks
+                    .on_action(&mut core.room, &core.players_cxts, p_id, action);
+                core.notify_all::<S>(diffs);
+                if let Some(input) = input {
+                    core.record(at, input);
+                }
+                core.apply_commands() || core.fire_timers::<S>()
+            }
+            RuntimeAction::Leave(id) => self.core.leave::<S>(id),
+            RuntimeAction::Join(cxt) => self.core.join::<S>(cxt),
+            RuntimeAction::Event(event) => self.core.event::<S>(event),
+            RuntimeAction::Finish => {
+                self.core.finish();
+                true
+            }
+            // Handled by RoomCore::wait
+            RuntimeAction::Wake => false,
+        }
+    }
+
+    fn turn_timeout<S: Schema>(&mut self, player_id: u64) -> bool
+    where
+        H::Delta: Serialize<S>,
+    {
+        // Forces a new time limit, even if the hooks keep the turn to the same player
+        self.turn = None;
+        let core = &mut self.core;
+        let at = core.room.clock().now();
+        let diffs = core
+            .hooks
+            .on_turn_timeout(&mut core.room, &core.players_cxts, player_id);
+        core.notify_all::<S>(diffs);
+        let tick = core.room.tick();
+        core.record(at, Input::TurnTimeout { tick, player_id });
+        core.apply_commands()
+    }
+}
+
+impl<H, S> GameRuntime<H, S> for TurnRuntime<H>
+where
+    H: TurnHooks,
+    S: Schema,
+    H::Delta: Serialize<S>,
+    H::Options: for<'a> Deserialize<'a, S>,
+    H::Action: for<'a> Deserialize<'a, S>,
+    for<'a> OutputMessage<'a>: Serialize<S>,
+{
+    type Handle = SyncGameHandle<H>;
+    type Settings = Settings<H>;
+
+    fn build(
+        type_: &'static str,
+        id: String,
+        seed: u64,
+        options: H::Options,
+        settings: &Self::Settings,
+        session_manager: Arc<SessionManager>,
+    ) -> Self {
+        let recorder = Recorder::start(
+            settings.record.as_ref(),
+            type_,
+            id.as_str(),
+            seed,
+            &options,
+            settings.clock.as_ref(),
+        );
+
+        Self {
+            core: RoomCore::new(
+                RoomContext::new(type_, id, seed, Arc::clone(&settings.clock)),
+                H::build(options),
+                session_manager,
+                recorder,
+            ),
+            turn: None,
+        }
+    }
+
+    fn start(mut self) -> Self::Handle {
+        SyncGameHandle::spawn(Arc::clone(self.core.room.clock()), move |action_rx| {
+            loop {
+                if self.core.check_finished::<S>() {
+                    break;
+                }
+                self.update_turn();
+
+                let turn = self
+                    .turn
+                    .and_then(|(player_id, deadline)| Some((player_id, deadline?)));
+                let is_stopped = match self
+                    .core
+                    .wait::<S>(&action_rx, turn.map(|(_, deadline)| deadline))
+                {
+                    Wake::Received(p_id, r_action) => self.process::<S>((p_id, r_action)),
+                    Wake::Deadline => {
+                        turn.is_some_and(|(player_id, _)| self.turn_timeout::<S>(player_id))
+                    }
+                    Wake::Timers => false,
+                    Wake::Stopped => true,
+                };
+                if is_stopped {
+                    break;
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,3 @@
+#[cfg(all(feature = "client", feature = "memory"))]
+pub mod e2e;
+pub mod room;
@@ -0,0 +1,184 @@
+use std::{ops::Deref, time::Duration};
+
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use crate::{
+    api::{
+        memory::MemoryConnector,
+        message::{InputMessage, OutputMessage},
+        schema::{Deserialize, Schema},
+    },
+    client::{
+        InternalEvent, ThundersClient, ThundersClientBuilder, core::GameHooks,
+        error::ThundersClientError, protocol::memory::MemoryClientProtocol,
+    },
+    server::{
+        ThundersServer, ThundersServerResult, handle::ServerHandle,
+        protocol::memory::MemoryProtocol,
+    },
+};
+
+// Serves the rooms in memory and hands out connected clients. Must be started within a tokio
+// runtime, dropping it shuts the server down in the background.
+pub struct TestKit<S>
+where
+    S: Schema,
+{
+    handle: ServerHandle<S>,
+    connector: MemoryConnector,
+    room_types: Vec<&'static str>,
+    timeout: Duration,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<ThundersServerResult>>,
+}
+
+impl<S> TestKit<S>
+where
+    S: Schema + Default + Send + Sync + 'static,
+    for<'a> InputMessage<'a>: Deserialize<'a, S>,
+    for<'a> OutputMessage<'a>: Deserialize<'a, S>,
+{
+    // Room types are registered through the setup, e.g. `|server| server.register(...)`.
+    pub fn start<F>(setup: F) -> Self
+    where
+        F: FnOnce(ThundersServer<MemoryProtocol, S>) -> ThundersServer<MemoryProtocol, S>,
+    {
+        let protocol = MemoryProtocol::new();
+        let connector = protocol.connector();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let (handle, server) =
+            setup(ThundersServer::new(protocol, S::default())).run_with_handle(async {
+                let _ = shutdown_rx.await;
+            });
+        let room_types = handle.room_types();
+        let server = tokio::spawn(server);
+
+        Self {
+            handle,
+            connector,
+            room_types,
+            timeout: Duration::from_secs(5),
+            shutdown: Some(shutdown_tx),
+            server: Some(server),
+        }
+    }
+
+    // Bounds connections, joins and waits of the clients created afterwards.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn handle(&self) -> &ServerHandle<S> {
+        &self.handle
+    }
+
+    // Connected as the given player, every registered room type can be created or joined.
+    pub async fn client(&self, player_id: u64) -> Result<TestClient<S>, ThundersClientError> {
+        let builder = self.room_types.iter().fold(
+            ThundersClientBuilder::new(
+                MemoryClientProtocol::new(self.connector.clone()),
+                S::default(),
+            ),
+            |builder, type_| builder.register(type_),
+        );
+        let client = builder.build().await?;
+        client.connect(player_id, self.timeout).await?;
+
+        Ok(TestClient {
+            player_id,
+            timeout: self.timeout,
+            client,
+        })
+    }
+
+    // Finishes every room and waits for the server to stop.
+    pub async fn shutdown(mut self) -> ThundersServerResult {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        match self.server.take() {
+            Some(server) => server.await.unwrap_or(Ok(())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S> Drop for TestKit<S>
+where
+    S: Schema,
+{
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+// Dereferences to the underlying client for create, join and actions.
+pub struct TestClient<S>
+where
+    S: Schema,
+{
+    player_id: u64,
+    timeout: Duration,
+    client: ThundersClient<S>,
+}
+
+impl<S> TestClient<S>
+where
+    S: Schema + 'static,
+{
+    pub fn player_id(&self) -> u64 {
+        self.player_id
+    }
+
+    pub async fn next_event(&self) -> Result<InternalEvent, ThundersClientError> {
+        tokio::time::timeout(self.timeout, self.client.consume_event())
+            .await
+            .map_err(|_| ThundersClientError::NoResponse)?
+    }
+
+    // Skips every other event until the room is updated.
+    pub async fn wait_room_updated(
+        &self,
+        type_: &str,
+        id: &str,
+    ) -> Result<(), ThundersClientError> {
+        tokio::time::timeout(self.timeout, async {
+            loop {
+                if let InternalEvent::RoomUpdated {
+                    type_: updated_type,
+                    id: updated_id,
+                } = self.client.consume_event().await?
+                    && updated_type == type_
+                    && updated_id == id
+                {
+                    return Ok(());
+                }
+            }
+        })
+        .await
+        .map_err(|_| ThundersClientError::NoResponse)?
+    }
+
+    // Reads the local game state, None if the room is not active.
+    pub fn state<G, T>(&self, type_: &'static str, id: &str, f: impl FnOnce(&G) -> T) -> Option<T>
+    where
+        G: GameHooks + Send + Sync + 'static,
+    {
+        let view = self.client.active_games.get_as::<G>(type_, id).ok()??;
+        Some(f(view.as_ref()))
+    }
+}
+
+impl<S> Deref for TestClient<S>
+where
+    S: Schema,
+{
+    type Target = ThundersClient<S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
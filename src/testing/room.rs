@@ -0,0 +1,253 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc, time::Duration};
+
+use crate::{
+    api::clock::ManualClock,
+    server::{
+        context::{PlayerContext, RoomCommand, RoomContext},
+        hooks::{Diff, GameHooks},
+    },
+};
+
+// Drives server hooks without runtime nor transport, on a manual clock. Diffs are resolved to
+// their recipients the way the runtime would and queued per player.
+pub struct RoomHarness<H>
+where
+    H: GameHooks,
+{
+    hooks: H,
+    room: RoomContext,
+    clock: ManualClock,
+    tick: Duration,
+    players_cxts: HashMap<u64, Arc<PlayerContext>>,
+    actions: Vec<(u64, H::Action)>,
+    received: HashMap<u64, Vec<H::Delta>>,
+    commands: Vec<RoomCommand>,
+    finished: bool,
+}
+
+impl<H> RoomHarness<H>
+where
+    H: GameHooks,
+    H::Delta: Clone,
+{
+    pub fn new(options: H::Options) -> Self {
+        let clock = ManualClock::new();
+        Self {
+            hooks: H::build(options),
+            room: RoomContext::new("test", "test".to_string(), 0, Arc::new(clock.clone())),
+            clock,
+            tick: Duration::from_millis(16),
+            players_cxts: HashMap::new(),
+            actions: vec![],
+            received: HashMap::new(),
+            commands: vec![],
+            finished: false,
+        }
+    }
+
+    // Must be called before any hook ran, the room context is built again.
+    pub fn with_room(mut self, type_: &'static str, id: impl Into<String>, seed: u64) -> Self {
+        self.room = RoomContext::new(type_, id.into(), seed, Arc::new(self.clock.clone()));
+        self
+    }
+
+    // Time the clock moves forward on every tick.
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    pub fn join(&mut self, player_id: u64) -> &mut Self {
+        self.join_with(PlayerContext::new(player_id))
+    }
+
+    pub fn join_with(&mut self, player_cxt: PlayerContext) -> &mut Self {
+        let cxt = Arc::new(player_cxt);
+        self.players_cxts.insert(cxt.id(), Arc::clone(&cxt));
+        let diffs = self.hooks.on_join(&mut self.room, cxt.as_ref());
+        self.deliver_all(diffs);
+        self.apply_commands()
+    }
+
+    pub fn leave(&mut self, player_id: u64) -> &mut Self {
+        if let Some(cxt) = self.players_cxts.remove(&player_id)
+            && let Some(diff) = self.hooks.on_leave(&mut self.room, cxt.as_ref())
+        {
+            self.deliver(diff);
+        }
+        self.apply_commands()
+    }
+
+    // Buffered until the next tick, like actions received by the runtime within a tick.
+    pub fn action(&mut self, player_id: u64, action: H::Action) -> &mut Self {
+        self.actions.push((player_id, action));
+        self
+    }
+
+    pub fn event(&mut self, event: H::Event) -> &mut Self {
+        let diffs = self
+            .hooks
+            .on_event(&mut self.room, &self.players_cxts, event);
+        self.deliver_all(diffs);
+        self.apply_commands()
+    }
+
+    // Moves the clock by one tick, runs on_tick with the buffered actions and fires due timers.
+    pub fn tick(&mut self) -> &mut Self {
+        self.clock.advance(self.tick);
+        self.room.advance();
+        let actions = std::mem::take(&mut self.actions);
+        let diffs = self
+            .hooks
+            .on_tick(&mut self.room, &self.players_cxts, actions);
+        self.deliver_all(diffs);
+        self.apply_commands();
+        self.fire_timers()
+    }
+
+    pub fn ticks(&mut self, count: u64) -> &mut Self {
+        for _ in 0..count {
+            self.tick();
+        }
+        self
+    }
+
+    // Moves the clock without ticking, only time based timers may fire.
+    pub fn advance(&mut self, duration: Duration) -> &mut Self {
+        self.clock.advance(duration);
+        self.fire_timers()
+    }
+
+    // Delivers the finishing diff the first time the hooks report the room as finished.
+    pub fn is_finished(&mut self) -> bool {
+        if !self.finished {
+            let (is_finished, diff) = self.hooks.is_finished();
+            if let Some(diff) = diff.filter(|_| is_finished) {
+                self.deliver(diff);
+            }
+            self.finished = is_finished;
+        }
+        self.finished
+    }
+
+    pub fn hooks(&self) -> &H {
+        &self.hooks
+    }
+
+    pub fn room(&self) -> &RoomContext {
+        &self.room
+    }
+
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    pub fn players(&self) -> Vec<u64> {
+        self.players_cxts.keys().copied().collect()
+    }
+
+    // Kicks, moves and finishes requested by the hooks so far, already applied.
+    pub fn commands(&self) -> &[RoomCommand] {
+        self.commands.as_slice()
+    }
+
+    pub fn received(&self, player_id: u64) -> &[H::Delta] {
+        self.received
+            .get(&player_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn take_received(&mut self, player_id: u64) -> Vec<H::Delta> {
+        self.received.remove(&player_id).unwrap_or_default()
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.received.clear();
+        self
+    }
+
+    // Drains what the player received and compares it with the expected deltas.
+    #[track_caller]
+    pub fn assert_received(&mut self, player_id: u64, expected: &[H::Delta]) -> &mut Self
+    where
+        H::Delta: PartialEq + Debug,
+    {
+        let received = self.take_received(player_id);
+        assert_eq!(
+            received.as_slice(),
+            expected,
+            "Player {player_id} received other diffs"
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_nothing_received(&mut self, player_id: u64) -> &mut Self
+    where
+        H::Delta: Debug,
+    {
+        let received = self.received(player_id);
+        assert!(
+            received.is_empty(),
+            "Player {player_id} should not have received diffs but got {received:?}"
+        );
+        self
+    }
+
+    // Drains what the player received, at least one delta has to match.
+    #[track_caller]
+    pub fn assert_any_received<F>(&mut self, player_id: u64, predicate: F) -> &mut Self
+    where
+        H::Delta: Debug,
+        F: Fn(&H::Delta) -> bool,
+    {
+        let received = self.take_received(player_id);
+        assert!(
+            received.iter().any(predicate),
+            "Player {player_id} received no matching diff in {received:?}"
+        );
+        self
+    }
+
+    fn deliver_all(&mut self, diffs: Option<Vec<Diff<H::Delta>>>) {
+        for diff in diffs.into_iter().flatten() {
+            self.deliver(diff);
+        }
+    }
+
+    fn deliver(&mut self, diff: Diff<H::Delta>) {
+        let (ids, delta) = match diff {
+            Diff::All { delta } => (self.players(), delta),
+            Diff::TargetUnique { id, delta } => (vec![id], delta),
+            Diff::TargetList { ids, delta } => (ids, delta),
+        };
+        for id in ids {
+            self.received.entry(id).or_default().push(delta.clone());
+        }
+    }
+
+    fn fire_timers(&mut self) -> &mut Self {
+        for timer_id in self.room.expired_timers() {
+            let diffs = self
+                .hooks
+                .on_timer(&mut self.room, &self.players_cxts, timer_id);
+            self.deliver_all(diffs);
+            self.apply_commands();
+        }
+        self
+    }
+
+    fn apply_commands(&mut self) -> &mut Self {
+        for command in self.room.take_commands() {
+            match &command {
+                RoomCommand::Kick { player_id, .. } | RoomCommand::Move { player_id, .. } => {
+                    self.players_cxts.remove(player_id);
+                }
+                RoomCommand::Finish => self.finished = true,
+            }
+            self.commands.push(command);
+        }
+        self
+    }
+}
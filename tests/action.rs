@@ -0,0 +1,205 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    client::InternalEvent,
+    server::{
+        context::{PlayerContext, RoomContext},
+        hooks::{ActionHooks, Diff, GameHooks},
+        runtime::action::{ActionRuntime, Settings},
+    },
+    testing::e2e::{TestClient, TestKit},
+};
+
+const ROOM_TYPE: &str = "log";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Push(i64);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Appended {
+    player_id: u64,
+    value: i64,
+    len: usize,
+}
+
+// Appends every action to a log shared by the room, negative values are refused.
+#[derive(Default)]
+struct LogServer {
+    log: Vec<(u64, i64)>,
+}
+
+impl GameHooks for LogServer {
+    type Delta = Appended;
+    type Action = Push;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+impl ActionHooks for LogServer {
+    fn validate_action(&self, _: u64, action: &Self::Action) -> Result<(), String> {
+        if action.0 < 0 {
+            return Err("Negative value".to_string());
+        }
+        Ok(())
+    }
+
+    fn on_action(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        player_id: u64,
+        action: Self::Action,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.log.push((player_id, action.0));
+        Some(vec![Diff::All {
+            delta: Appended {
+                player_id,
+                value: action.0,
+                len: self.log.len(),
+            },
+        }])
+    }
+}
+
+#[derive(Default)]
+struct LogClient {
+    appended: Vec<Appended>,
+    sent: Vec<u64>,
+    rejected: Vec<u64>,
+}
+
+impl thunders::client::core::GameHooks for LogClient {
+    type Change = Appended;
+    type Action = Push;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.appended.push(change);
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+
+    fn on_sequenced_action(&mut self, seq: u64, _: Self::Action) {
+        self.sent.push(seq);
+    }
+
+    fn on_rejected_action(&mut self, seq: u64) {
+        self.rejected.push(seq);
+    }
+}
+
+async fn start() -> (TestKit<Json>, TestClient<Json>) {
+    let kit = TestKit::start(|server| {
+        server.register::<ActionRuntime<_>, LogServer>(ROOM_TYPE, Settings::default())
+    });
+    let client = kit.client(1).await.unwrap();
+    client
+        .create::<LogClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+    (kit, client)
+}
+
+#[tokio::test]
+async fn actions_are_handled_one_at_a_time_in_arrival_order() {
+    let (kit, first) = start().await;
+    let second = kit.client(2).await.unwrap();
+    second
+        .join::<LogClient>(ROOM_TYPE, "room", TIMEOUT)
+        .await
+        .unwrap();
+
+    for value in 0..10 {
+        first
+            .action::<LogClient>(ROOM_TYPE, "room", Push(value))
+            .unwrap();
+    }
+    for _ in 0..10 {
+        second.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    }
+
+    // Every action got its own diff, right after the previous one
+    let appended = second
+        .state::<LogClient, _>(ROOM_TYPE, "room", |log| {
+            log.appended
+                .iter()
+                .map(|appended| (appended.player_id, appended.value, appended.len))
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+    let expected = (0..10)
+        .map(|value| (1, value, value as usize + 1))
+        .collect::<Vec<_>>();
+    assert_eq!(appended, expected);
+}
+
+#[tokio::test]
+async fn rejections_reach_the_client_with_their_sequence_number() {
+    let (_kit, client) = start().await;
+
+    for value in [1, -1, 2] {
+        client
+            .action::<LogClient>(ROOM_TYPE, "room", Push(value))
+            .unwrap();
+    }
+    loop {
+        if let InternalEvent::ActionRejected { reason, .. } = client.next_event().await.unwrap() {
+            assert_eq!(reason, "Negative value");
+            break;
+        }
+    }
+    client.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+
+    let (sent, rejected, values) = client
+        .state::<LogClient, _>(ROOM_TYPE, "room", |log| {
+            (
+                log.sent.clone(),
+                log.rejected.clone(),
+                log.appended
+                    .iter()
+                    .map(|appended| appended.value)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .unwrap();
+    assert_eq!(rejected, vec![sent[1]]);
+    assert_eq!(values, vec![1, 2]);
+}
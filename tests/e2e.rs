@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::{clock::ManualClock, schema::json::Json},
+    server::{
+        context::{PlayerContext, RoomContext},
+        hooks::{Diff, GameHooks},
+        runtime::sync::{Settings, SyncRuntime},
+    },
+    testing::e2e::TestKit,
+};
+
+const ROOM_TYPE: &str = "tally";
+const TIMEOUT: Duration = Duration::from_secs(5);
+const TICK: Duration = Duration::from_millis(10);
+const IDLE_TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Add(u64);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Tally {
+    tick: u64,
+    total: u64,
+}
+
+// Sends the total of every action so far on each tick, idle ones included.
+#[derive(Default)]
+struct TallyServer {
+    total: u64,
+}
+
+impl GameHooks for TallyServer {
+    type Delta = Tally;
+    type Action = Add;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_tick(
+        &mut self,
+        room: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        actions: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.total += actions.iter().map(|(_, Add(value))| value).sum::<u64>();
+        Some(vec![Diff::All {
+            delta: Tally {
+                tick: room.tick(),
+                total: self.total,
+            },
+        }])
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+#[derive(Default)]
+struct TallyClient {
+    last: Option<(u64, u64)>,
+}
+
+impl thunders::client::core::GameHooks for TallyClient {
+    type Change = Tally;
+    type Action = Add;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.last = Some((change.tick, change.total));
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+fn start(clock: &ManualClock) -> TestKit<Json> {
+    let clock = Arc::new(clock.clone());
+    TestKit::start(move |server| {
+        server.register::<SyncRuntime<_>, TallyServer>(
+            ROOM_TYPE,
+            Settings {
+                tick_millis: TICK.as_millis() as u64,
+                tick_no_action_millis: IDLE_TICK.as_millis() as u64,
+                clock,
+                ..Default::default()
+            },
+        )
+    })
+}
+
+#[tokio::test]
+async fn rooms_tick_when_the_manual_clock_moves() {
+    let clock = ManualClock::new();
+    let kit = start(&clock);
+    let alice = kit.client(1).await.unwrap();
+    alice
+        .create::<TallyClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+    let bob = kit.client(2).await.unwrap();
+    bob.join::<TallyClient>(ROOM_TYPE, "room", TIMEOUT)
+        .await
+        .unwrap();
+
+    alice
+        .action::<TallyClient>(ROOM_TYPE, "room", Add(2))
+        .unwrap();
+    bob.action::<TallyClient>(ROOM_TYPE, "room", Add(3))
+        .unwrap();
+    // Real time does not move the room
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(
+        alice.state::<TallyClient, _>(ROOM_TYPE, "room", |tally| tally.last),
+        Some(None)
+    );
+
+    clock.advance(TICK);
+    alice.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    bob.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    for client in [&alice, &bob] {
+        assert_eq!(
+            client.state::<TallyClient, _>(ROOM_TYPE, "room", |tally| tally.last),
+            Some(Some((1, 5)))
+        );
+    }
+
+    clock.advance(IDLE_TICK);
+    alice.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    assert_eq!(
+        alice.state::<TallyClient, _>(ROOM_TYPE, "room", |tally| tally.last),
+        Some(Some((2, 5)))
+    );
+
+    assert!(kit.shutdown().await.is_ok());
+}
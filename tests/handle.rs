@@ -0,0 +1,153 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    server::{
+        context::{PlayerContext, RoomContext},
+        error::ThundersServerError,
+        hooks::{Diff, GameHooks},
+        runtime::sync::{Settings, SyncRuntime},
+    },
+    testing::e2e::TestKit,
+};
+
+const ROOM_TYPE: &str = "notes";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Note(String);
+
+// Only relays what the server handle sends.
+struct NotesServer;
+
+impl GameHooks for NotesServer {
+    type Delta = Note;
+    type Action = Note;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+#[derive(Default)]
+struct NotesClient {
+    notes: Vec<String>,
+}
+
+impl thunders::client::core::GameHooks for NotesClient {
+    type Change = Note;
+    type Action = Note;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.notes.push(change.0);
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+fn start() -> TestKit<Json> {
+    TestKit::start(|server| {
+        server.register::<SyncRuntime<_>, NotesServer>(ROOM_TYPE, Settings::default())
+    })
+}
+
+#[tokio::test]
+async fn send_only_reaches_players_of_the_room() {
+    let kit = start();
+    let client = kit.client(1).await.unwrap();
+    client
+        .create::<NotesClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        kit.handle()
+            .send(1, ROOM_TYPE, "other", Note("lost".into())),
+        Err(ThundersServerError::PlayerNotFound)
+    ));
+    kit.handle()
+        .send(1, ROOM_TYPE, "room", Note("hello".into()))
+        .unwrap();
+
+    client.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    assert_eq!(
+        client.state::<NotesClient, _>(ROOM_TYPE, "room", |notes| notes.notes.clone()),
+        Some(vec!["hello".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn kick_closes_the_player_connection() {
+    let kit = start();
+    let client = kit.client(1).await.unwrap();
+    client
+        .create::<NotesClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+
+    kit.handle().kick(1).unwrap();
+
+    // Events end once the connection is closed
+    tokio::time::timeout(TIMEOUT, async {
+        while client.consume_event().await.is_ok() {}
+    })
+    .await
+    .expect("Should close the connection of the kicked player");
+    assert!(kit.handle().players().is_empty());
+}
+
+#[tokio::test]
+async fn close_room_stops_the_room() {
+    let kit = start();
+    let client = kit.client(1).await.unwrap();
+    client
+        .create::<NotesClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+
+    kit.handle().close_room(ROOM_TYPE, "room").await.unwrap();
+    assert!(kit.handle().rooms().is_empty());
+    assert!(matches!(
+        kit.handle().send(1, ROOM_TYPE, "room", Note("lost".into())),
+        Err(ThundersServerError::PlayerNotFound)
+    ));
+    assert!(matches!(
+        kit.handle().close_room(ROOM_TYPE, "room").await,
+        Err(ThundersServerError::RoomNotFound)
+    ));
+}
@@ -0,0 +1,167 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use thunders::{
+    server::{
+        context::{Delay, PlayerContext, RoomCommand, RoomContext},
+        hooks::{Diff, GameHooks},
+    },
+    testing::room::RoomHarness,
+};
+
+const TARGET: i64 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Delta {
+    Welcome(u64),
+    Total(i64),
+    Hurry,
+}
+
+// Players add to a shared total until it reaches the target. Negative additions get the player
+// kicked and the room hurries its players after a few seconds.
+#[derive(Default)]
+struct RaceServer {
+    total: i64,
+}
+
+impl GameHooks for RaceServer {
+    type Delta = Delta;
+    type Action = i64;
+    type Options = ();
+    type Event = i64;
+
+    fn build(_: Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_tick(
+        &mut self,
+        room: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        actions: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        if actions.is_empty() {
+            return None;
+        }
+        for (player_id, value) in actions {
+            if value < 0 {
+                room.kick(player_id, "Negative addition");
+                continue;
+            }
+            self.total += value;
+        }
+        Some(vec![Diff::All {
+            delta: Delta::Total(self.total),
+        }])
+    }
+
+    fn on_join(
+        &mut self,
+        room: &mut RoomContext,
+        player: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        room.set_timeout(Delay::Duration(Duration::from_secs(5)));
+        Some(vec![Diff::TargetUnique {
+            id: player.id(),
+            delta: Delta::Welcome(player.id()),
+        }])
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn on_timer(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: u64,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        Some(vec![Diff::All {
+            delta: Delta::Hurry,
+        }])
+    }
+
+    fn on_event(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        bonus: Self::Event,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.total += bonus;
+        Some(vec![Diff::All {
+            delta: Delta::Total(self.total),
+        }])
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        let is_finished = self.total >= TARGET;
+        (
+            is_finished,
+            is_finished.then_some(Diff::All {
+                delta: Delta::Total(self.total),
+            }),
+        )
+    }
+}
+
+#[test]
+fn actions_of_a_tick_are_handed_together() {
+    let mut harness = RoomHarness::<RaceServer>::new(());
+    harness.join(1).join(2);
+    harness
+        .assert_received(1, &[Delta::Welcome(1)])
+        .assert_received(2, &[Delta::Welcome(2)]);
+
+    harness.action(1, 2).action(2, 3);
+    harness.assert_nothing_received(1);
+    harness
+        .tick()
+        .assert_received(1, &[Delta::Total(5)])
+        .assert_received(2, &[Delta::Total(5)]);
+    assert_eq!(harness.room().tick(), 1);
+}
+
+#[test]
+fn timers_fire_once_the_clock_reached_them() {
+    let mut harness = RoomHarness::<RaceServer>::new(()).with_tick(Duration::from_secs(1));
+    harness.join(1).clear();
+
+    harness.ticks(4).assert_nothing_received(1);
+    harness
+        .advance(Duration::from_secs(1))
+        .assert_received(1, &[Delta::Hurry]);
+    assert_eq!(harness.clock().elapsed(), Duration::from_secs(5));
+}
+
+#[test]
+fn kicked_players_stop_receiving_diffs() {
+    let mut harness = RoomHarness::<RaceServer>::new(());
+    harness.join(1).join(2).clear();
+
+    harness.action(1, -1).action(2, 4).tick();
+    assert_eq!(
+        harness.commands(),
+        &[RoomCommand::Kick {
+            player_id: 1,
+            reason: "Negative addition".to_string(),
+        }]
+    );
+    assert_eq!(harness.players(), vec![2]);
+    // Commands apply once the diffs of the hook were sent
+    harness.assert_received(1, &[Delta::Total(4)]);
+
+    harness.event(1).assert_nothing_received(1);
+    harness.assert_received(2, &[Delta::Total(4), Delta::Total(5)]);
+}
+
+#[test]
+fn finishing_diff_is_delivered_once() {
+    let mut harness = RoomHarness::<RaceServer>::new(());
+    harness.join(1).clear();
+
+    harness.action(1, TARGET).tick();
+    assert!(harness.is_finished());
+    assert!(harness.is_finished());
+    harness.assert_received(1, &[Delta::Total(TARGET), Delta::Total(TARGET)]);
+}
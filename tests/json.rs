@@ -0,0 +1,22 @@
+use thunders::api::{
+    message::OutputMessage,
+    schema::{Deserialize, Serialize, json::Json},
+};
+
+#[test]
+fn kick_reasons_with_escapes_round_trip() {
+    let reason = "said \"bye\"\n\tand left";
+    let raw = <OutputMessage as Serialize<Json>>::serialize(OutputMessage::Kick {
+        type_: "room",
+        id: "1",
+        reason: reason.into(),
+    });
+
+    let Ok(OutputMessage::Kick {
+        reason: received, ..
+    }) = <OutputMessage as Deserialize<Json>>::deserialize(raw.as_slice())
+    else {
+        panic!("Should deserialize the kick message");
+    };
+    assert_eq!(received, reason);
+}
@@ -0,0 +1,223 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    client::{InternalEvent, lockstep::LockstepClient},
+    server::{
+        context::{PlayerContext, RoomContext},
+        hooks::{Diff, FrameHooks, GameHooks, LockstepHooks},
+        runtime::lockstep::{LockstepRuntime, Settings},
+    },
+    testing::e2e::{TestClient, TestKit},
+};
+
+const ROOM_TYPE: &str = "march";
+const TIMEOUT: Duration = Duration::from_secs(5);
+const INPUT_DELAY: u64 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Step {
+    frame: u64,
+    value: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Frame {
+    frame: u64,
+    inputs: Vec<(u64, i64)>,
+}
+
+// Broadcasts the inputs of every frame as they are.
+struct MarchServer;
+
+impl GameHooks for MarchServer {
+    type Delta = Frame;
+    type Action = Step;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+impl FrameHooks for MarchServer {
+    type Input = i64;
+
+    fn frame_input(action: Self::Action) -> (u64, Self::Input) {
+        (action.frame, action.value)
+    }
+}
+
+impl LockstepHooks for MarchServer {
+    fn on_frame(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        frame: u64,
+        inputs: Vec<(u64, Self::Input)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        Some(vec![Diff::All {
+            delta: Frame { frame, inputs },
+        }])
+    }
+}
+
+// Simulates the frames in order, recording the inputs of each.
+struct MarchClient {
+    lockstep: LockstepClient<i64>,
+    simulated: Vec<(u64, Vec<(u64, i64)>)>,
+}
+
+impl thunders::client::core::GameHooks for MarchClient {
+    type Change = Frame;
+    type Action = Step;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self {
+            lockstep: LockstepClient::new(INPUT_DELAY),
+            simulated: vec![],
+        }
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.lockstep.receive(change.frame, change.inputs);
+        while let Some(frame) = self.lockstep.next_frame() {
+            self.simulated.push(frame);
+        }
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+async fn start(frame_timeout_millis: u64) -> (TestKit<Json>, TestClient<Json>, TestClient<Json>) {
+    let kit = TestKit::start(move |server| {
+        server.register::<LockstepRuntime<_>, MarchServer>(
+            ROOM_TYPE,
+            Settings {
+                frame_timeout_millis,
+                input_delay: INPUT_DELAY,
+                ..Default::default()
+            },
+        )
+    });
+    let first = kit.client(1).await.unwrap();
+    let second = kit.client(2).await.unwrap();
+    first
+        .create::<MarchClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+    second
+        .join::<MarchClient>(ROOM_TYPE, "room", TIMEOUT)
+        .await
+        .unwrap();
+    (kit, first, second)
+}
+
+fn send(client: &TestClient<Json>, frame: u64, value: i64) {
+    client
+        .action::<MarchClient>(ROOM_TYPE, "room", Step { frame, value })
+        .unwrap();
+}
+
+// Sends the input of the frame simulated next, as a game loop would.
+fn step(client: &TestClient<Json>, value: i64) {
+    let frame = client
+        .state::<MarchClient, _>(ROOM_TYPE, "room", |march| march.lockstep.input_frame())
+        .unwrap();
+    send(client, frame, value);
+}
+
+fn simulated(client: &TestClient<Json>) -> Vec<(u64, Vec<(u64, i64)>)> {
+    client
+        .state::<MarchClient, _>(ROOM_TYPE, "room", |march| march.simulated.clone())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn players_advance_together() {
+    // Stragglers are waited for long enough to never time out here
+    let (_kit, first, second) = start(60_000).await;
+
+    step(&first, 1);
+    step(&second, 2);
+    first.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    second.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+
+    let expected = vec![(0, vec![]), (1, vec![]), (2, vec![(1, 1), (2, 2)])];
+    assert_eq!(simulated(&first), expected);
+    assert_eq!(simulated(&second), expected);
+}
+
+#[tokio::test]
+async fn stragglers_are_left_out_once_the_frame_timed_out() {
+    let (_kit, first, second) = start(50).await;
+
+    step(&first, 1);
+    first.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    second.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    assert_eq!(
+        simulated(&second),
+        vec![(0, vec![]), (1, vec![]), (2, vec![(1, 1)])]
+    );
+
+    // Too late for the frame done without it, the next one waits for it again
+    send(&second, 2, 2);
+    wait_rejected(&second).await;
+    send(&first, 3, 3);
+    send(&second, 3, 4);
+    first.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    assert_eq!(simulated(&first)[3], (3, vec![(1, 3), (2, 4)]));
+}
+
+#[tokio::test]
+async fn frames_within_the_input_delay_are_empty() {
+    let mut lockstep = LockstepClient::<i64>::new(INPUT_DELAY);
+    assert_eq!(lockstep.input_frame(), INPUT_DELAY);
+    assert_eq!(lockstep.ready(), 2);
+    assert_eq!(lockstep.next_frame(), Some((0, vec![])));
+    assert_eq!(lockstep.next_frame(), Some((1, vec![])));
+    assert_eq!(lockstep.next_frame(), None);
+
+    // The room has no frame within the delay to send inputs for
+    let (_kit, first, _second) = start(60_000).await;
+    send(&first, 1, 1);
+    wait_rejected(&first).await;
+}
+
+async fn wait_rejected(client: &TestClient<Json>) {
+    loop {
+        if let InternalEvent::ActionRejected { .. } = client.next_event().await.unwrap() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    client::{core::GameHooks as _, prediction::Predicted},
+    server::{
+        context::{PlayerContext, RoomContext},
+        hooks::{ActionHooks, Diff, GameHooks},
+        runtime::action::{ActionRuntime, Settings},
+    },
+    testing::e2e::TestKit,
+};
+
+const ROOM_TYPE: &str = "counter";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Add(i64);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Total(i64);
+
+#[derive(Debug, Default, Clone)]
+struct Counter {
+    total: i64,
+    // Sequence numbers of the actions sent
+    sent: Vec<u64>,
+}
+
+impl thunders::client::core::GameHooks for Counter {
+    type Change = Total;
+    type Action = Add;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.total = change.0;
+    }
+
+    fn on_action(&mut self, action: Self::Action) {
+        self.total += action.0;
+    }
+
+    fn on_finish(self) {}
+
+    fn on_sequenced_action(&mut self, seq: u64, action: Self::Action) {
+        self.sent.push(seq);
+        self.on_action(action);
+    }
+}
+
+fn pending(counter: &Predicted<Counter>) -> Vec<Add> {
+    counter.pending().cloned().collect()
+}
+
+#[test]
+fn acknowledged_actions_are_dropped_and_the_rest_applied_again() {
+    let mut counter = Predicted::<Counter>::build(&());
+    counter.on_sequenced_action(1, Add(1));
+    counter.on_sequenced_action(2, Add(10));
+    counter.on_sequenced_action(3, Add(100));
+    assert_eq!(counter.predicted().total, 111);
+
+    // The room applied the first two actions along with 1000 from another player
+    counter.on_acknowledged_change(Some(2), Total(1011));
+    assert_eq!(pending(&counter), vec![Add(100)]);
+    assert_eq!(counter.authoritative().total, 1011);
+    assert_eq!(counter.predicted().total, 1111);
+
+    // Diffs of other players acknowledge nothing
+    counter.on_acknowledged_change(None, Total(1012));
+    assert_eq!(pending(&counter), vec![Add(100)]);
+    assert_eq!(counter.predicted().total, 1112);
+
+    counter.on_acknowledged_change(Some(3), Total(1112));
+    assert!(pending(&counter).is_empty());
+    assert_eq!(counter.predicted().total, 1112);
+}
+
+#[test]
+fn rejections_only_drop_their_action() {
+    let mut counter = Predicted::<Counter>::build(&());
+    counter.on_sequenced_action(1, Add(1));
+    counter.on_sequenced_action(2, Add(10));
+    counter.on_sequenced_action(3, Add(100));
+
+    counter.on_rejected_action(2);
+    assert_eq!(pending(&counter), vec![Add(1), Add(100)]);
+    assert_eq!(counter.predicted().total, 101);
+    assert_eq!(counter.authoritative().total, 0);
+}
+
+// Adds every action to the total.
+#[derive(Default)]
+struct CounterServer {
+    total: i64,
+}
+
+impl GameHooks for CounterServer {
+    type Delta = Total;
+    type Action = Add;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+impl ActionHooks for CounterServer {
+    fn on_action(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: u64,
+        action: Self::Action,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.total += action.0;
+        Some(vec![Diff::All {
+            delta: Total(self.total),
+        }])
+    }
+}
+
+#[tokio::test]
+async fn sequence_numbers_increase_across_actions_and_rooms() {
+    let kit = TestKit::<Json>::start(|server| {
+        server.register::<ActionRuntime<_>, CounterServer>(ROOM_TYPE, Settings::default())
+    });
+    let client = kit.client(1).await.unwrap();
+    for room in ["first", "second"] {
+        client
+            .create::<Counter>(ROOM_TYPE, room, (), TIMEOUT)
+            .await
+            .unwrap();
+    }
+
+    for (room, value) in [("first", 1), ("second", 2), ("first", 3)] {
+        client
+            .action::<Counter>(ROOM_TYPE, room, Add(value))
+            .unwrap();
+    }
+
+    let sent = |room| {
+        client
+            .state::<Counter, _>(ROOM_TYPE, room, |counter| counter.sent.clone())
+            .unwrap()
+    };
+    let (first, second) = (sent("first"), sent("second"));
+    assert_eq!(first.len(), 2);
+    assert_eq!(second.len(), 1);
+    assert!(first[0] < second[0] && second[0] < first[1]);
+}
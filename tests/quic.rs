@@ -0,0 +1,183 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use quinn::{
+    ClientConfig, Connection, Endpoint, RecvStream, SendStream,
+    rustls::{RootCertStore, pki_types::CertificateDer},
+};
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::{
+        framing::{read_frame, write_frame},
+        message::{Delivery, InputMessage, OutputMessage},
+        schema::{Deserialize as SchemaDeserialize, Serialize as SchemaSerialize, json::Json},
+    },
+    server::{
+        ThundersServer,
+        context::{PlayerContext, RoomContext},
+        hooks::{Diff, GameHooks},
+        protocol::quic::QuicProtocol,
+        runtime::sync::{Settings, SyncRuntime},
+    },
+};
+
+const ROOM_TYPE: &str = "mixed";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Delta {
+    // Unreliable and small enough for a datagram
+    Position(u32),
+    // Unreliable but larger than any datagram
+    Snapshot(String),
+    Score(u32),
+}
+
+// Welcomes every player with one delta of each kind.
+struct MixedServer;
+
+impl GameHooks for MixedServer {
+    type Delta = Delta;
+    type Action = ();
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        Some(
+            [
+                Delta::Position(1),
+                Delta::Snapshot("s".repeat(10_000)),
+                Delta::Score(2),
+            ]
+            .into_iter()
+            .map(|delta| Diff::All { delta })
+            .collect(),
+        )
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+
+    fn delivery(delta: &Self::Delta) -> Delivery {
+        match delta {
+            Delta::Position(_) | Delta::Snapshot(_) => Delivery::Unreliable,
+            Delta::Score(_) => Delivery::Reliable,
+        }
+    }
+}
+
+fn start_server() -> (u16, CertificateDer<'static>) {
+    let port = std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let protocol = QuicProtocol::new("127.0.0.1", port);
+    let certificate = protocol.certificate().clone();
+    let server = ThundersServer::new(protocol, Json::default())
+        .register::<SyncRuntime<_>, MixedServer>(ROOM_TYPE, Settings::default());
+    tokio::spawn(server.run());
+    (port, certificate)
+}
+
+// Talks to the server with plain quinn, so the test sees which way every message came.
+async fn connect(port: u16, certificate: CertificateDer<'static>) -> Connection {
+    let mut roots = RootCertStore::empty();
+    roots.add(certificate).unwrap();
+    let mut endpoint = Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+    endpoint
+        .set_default_client_config(ClientConfig::with_root_certificates(Arc::new(roots)).unwrap());
+    let addr: SocketAddr = ([127, 0, 0, 1], port).into();
+
+    // Retried until the server is listening
+    loop {
+        let connecting = endpoint.connect(addr, "localhost").unwrap();
+        if let Ok(Ok(connection)) =
+            tokio::time::timeout(Duration::from_millis(200), connecting).await
+        {
+            return connection;
+        }
+    }
+}
+
+async fn send(send: &mut SendStream, message: InputMessage<'_>) {
+    write_frame(send, &SchemaSerialize::<Json>::serialize(message))
+        .await
+        .unwrap();
+}
+
+fn delta(raw_message: &[u8]) -> Option<Delta> {
+    match <OutputMessage as SchemaDeserialize<Json>>::deserialize(raw_message).unwrap() {
+        OutputMessage::Diff { data, .. } => Some(serde_json::from_slice(data).unwrap()),
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn unreliable_diffs_fitting_a_datagram_skip_the_stream() {
+    let (port, certificate) = start_server();
+    let connection = tokio::time::timeout(TIMEOUT, connect(port, certificate))
+        .await
+        .expect("Should connect to the server");
+    let (mut send_stream, mut recv): (SendStream, RecvStream) = connection.open_bi().await.unwrap();
+
+    send(
+        &mut send_stream,
+        InputMessage::Connect {
+            correlation_id: "connect",
+            id: 1,
+        },
+    )
+    .await;
+    send(
+        &mut send_stream,
+        InputMessage::Create {
+            correlation_id: "create",
+            type_: ROOM_TYPE,
+            id: "room",
+            options: None,
+            seed: None,
+        },
+    )
+    .await;
+
+    let datagram = tokio::time::timeout(TIMEOUT, connection.read_datagram())
+        .await
+        .expect("Should receive the small unreliable diff as a datagram")
+        .unwrap();
+    assert_eq!(delta(&datagram), Some(Delta::Position(1)));
+
+    let mut streamed = vec![];
+    while streamed.len() < 2 {
+        let raw_message = tokio::time::timeout(TIMEOUT, read_frame(&mut recv))
+            .await
+            .expect("Should receive the other diffs on the stream")
+            .unwrap();
+        streamed.extend(delta(&raw_message));
+    }
+    assert_eq!(
+        streamed,
+        vec![Delta::Snapshot("s".repeat(10_000)), Delta::Score(2)]
+    );
+}
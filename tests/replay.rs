@@ -0,0 +1,249 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    server::{
+        ThundersServer,
+        context::{Delay, PlayerContext, RoomContext},
+        hooks::{ActionHooks, Diff, GameHooks},
+        protocol::memory::MemoryProtocol,
+        replay::{Input, RecordSettings, Recording, ReplayError},
+        runtime::{action::ActionRuntime, sync::SyncRuntime},
+    },
+    testing::e2e::TestKit,
+};
+
+const ROOM_TYPE: &str = "dice";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Roll(u64);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Total(u64);
+
+// Rolls dice on actions, events add a bonus and a timer rolls one more die now and then, so the
+// total only replays the same with every input at its time.
+struct DiceServer {
+    faces: u64,
+    total: u64,
+}
+
+impl GameHooks for DiceServer {
+    type Delta = Total;
+    type Action = Roll;
+    type Options = u64;
+    type Event = u64;
+
+    fn build(faces: Self::Options) -> Self {
+        Self { faces, total: 0 }
+    }
+
+    fn on_tick(
+        &mut self,
+        room: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        actions: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        if actions.is_empty() {
+            return None;
+        }
+        for (_, Roll(dice)) in actions {
+            for _ in 0..dice {
+                self.total += room.rng().range(1..self.faces + 1);
+            }
+        }
+        Some(vec![Diff::All {
+            delta: Total(self.total),
+        }])
+    }
+
+    fn on_join(
+        &mut self,
+        room: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        room.set_interval(Delay::Duration(Duration::from_millis(20)));
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn on_timer(
+        &mut self,
+        room: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: u64,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.total += room.rng().range(1..self.faces + 1);
+        Some(vec![Diff::All {
+            delta: Total(self.total),
+        }])
+    }
+
+    fn on_event(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        bonus: Self::Event,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.total += bonus;
+        Some(vec![Diff::All {
+            delta: Total(self.total),
+        }])
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+impl ActionHooks for DiceServer {
+    fn on_action(
+        &mut self,
+        room: &mut RoomContext,
+        players_cxts: &HashMap<u64, Arc<PlayerContext>>,
+        player_id: u64,
+        action: Self::Action,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.on_tick(room, players_cxts, vec![(player_id, action)])
+    }
+}
+
+struct DiceClient;
+
+impl thunders::client::core::GameHooks for DiceClient {
+    type Change = Total;
+    type Action = Roll;
+    type Options = u64;
+
+    fn build(_: &Self::Options) -> Self {
+        Self
+    }
+
+    fn on_change(&mut self, _: Self::Change) {}
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+type Server = ThundersServer<MemoryProtocol, Json>;
+
+fn sync_room(server: Server, record: RecordSettings<DiceServer>) -> Server {
+    server.register::<SyncRuntime<_>, DiceServer>(
+        ROOM_TYPE,
+        thunders::server::runtime::sync::Settings {
+            tick_millis: 5,
+            tick_no_action_millis: 15,
+            record: Some(record),
+            ..Default::default()
+        },
+    )
+}
+
+fn action_room(server: Server, record: RecordSettings<DiceServer>) -> Server {
+    server.register::<ActionRuntime<_>, DiceServer>(
+        ROOM_TYPE,
+        thunders::server::runtime::action::Settings {
+            tick_millis: Some(5),
+            record: Some(record),
+            ..Default::default()
+        },
+    )
+}
+
+// Plays a room recorded into its own directory and reads the recording back once closed.
+async fn record(
+    name: &str,
+    register: fn(Server, RecordSettings<DiceServer>) -> Server,
+) -> Recording {
+    let dir = std::env::temp_dir().join(format!("thunders-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let record_dir = dir.clone();
+    let kit =
+        TestKit::start(move |server| register(server, RecordSettings::new::<Json>(record_dir)));
+    let client = kit.client(1).await.unwrap();
+    client
+        .create::<DiceClient>(ROOM_TYPE, "room", 6, TIMEOUT)
+        .await
+        .unwrap();
+
+    for dice in 1..=5 {
+        client
+            .action::<DiceClient>(ROOM_TYPE, "room", Roll(dice))
+            .unwrap();
+        kit.handle().send_event(ROOM_TYPE, "room", dice).unwrap();
+        tokio::time::sleep(Duration::from_millis(15)).await;
+    }
+    kit.handle().close_room(ROOM_TYPE, "room").await.unwrap();
+
+    let path = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .expect("Should write a recording for the room")
+        .unwrap()
+        .path();
+    let recording = Recording::read(path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    recording
+}
+
+#[tokio::test]
+async fn replays_a_recorded_room() {
+    let recording = record("replays", sync_room).await;
+
+    let has = |f: fn(&Input) -> bool| recording.records.iter().any(|record| f(&record.input));
+    assert!(has(
+        |input| matches!(input, Input::Tick { actions, .. } if !actions.is_empty())
+    ));
+    assert!(has(|input| matches!(input, Input::Timer { .. })));
+    assert!(has(|input| matches!(input, Input::Event { .. })));
+
+    assert!(matches!(
+        recording.replay::<DiceServer, Json>(ROOM_TYPE),
+        Ok(())
+    ));
+}
+
+#[tokio::test]
+async fn replay_reports_the_first_diverging_record() {
+    let mut recording = record("diverging", sync_room).await;
+
+    let (index, actions) = recording
+        .records
+        .iter_mut()
+        .enumerate()
+        .find_map(|(index, record)| match &mut record.input {
+            Input::Tick { actions, .. } if !actions.is_empty() => Some((index, actions)),
+            _ => None,
+        })
+        .unwrap();
+    actions[0].1 = serde_json::to_vec(&Roll(100)).unwrap();
+
+    assert!(matches!(
+        recording.replay::<DiceServer, Json>(ROOM_TYPE),
+        Err(ReplayError::Mismatch { record }) if record == index
+    ));
+}
+
+#[tokio::test]
+async fn replays_a_recorded_action_room() {
+    let recording = record("actions", action_room).await;
+
+    assert!(recording.records.iter().any(
+        |record| matches!(&record.input, Input::Action { player_id: 1, action, .. } if !action.is_empty())
+    ));
+    assert!(matches!(
+        recording.replay_actions::<DiceServer, Json>(ROOM_TYPE),
+        Ok(())
+    ));
+    assert!(matches!(
+        recording.replay::<DiceServer, Json>(ROOM_TYPE),
+        Err(ReplayError::RuntimeMismatch)
+    ));
+}
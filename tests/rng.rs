@@ -0,0 +1,50 @@
+use thunders::server::context::RoomRng;
+
+fn draws(seed: u64) -> Vec<u64> {
+    let mut rng = RoomRng::new(seed);
+    (0..64).map(|_| rng.next_u64()).collect()
+}
+
+#[test]
+fn same_seed_draws_the_same_sequence() {
+    assert_eq!(draws(42), draws(42));
+    assert_ne!(draws(42), draws(43));
+}
+
+// Recordings replay with the seed only, the sequence must never change across releases.
+#[test]
+fn sequence_is_splitmix64() {
+    let mut rng = RoomRng::new(0);
+    assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+    assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+}
+
+#[test]
+fn ranges_stay_in_bounds() {
+    let mut rng = RoomRng::new(7);
+    for range in [0..1, 1..7, 10..13, 0..1000, 5..u64::MAX, 0..(1 << 63) + 1] {
+        for _ in 0..1000 {
+            let value = rng.range(range.clone());
+            assert!(range.contains(&value), "{value} out of {range:?}");
+        }
+    }
+}
+
+#[test]
+fn ranges_cover_every_value() {
+    let mut rng = RoomRng::new(7);
+    let mut counts = [0u32; 6];
+    for _ in 0..6000 {
+        counts[rng.range(1..7) as usize - 1] += 1;
+    }
+    assert!(counts.iter().all(|count| (800..1200).contains(count)));
+}
+
+#[test]
+fn ranges_replay_the_same_for_a_seed() {
+    let roll = |seed| {
+        let mut rng = RoomRng::new(seed);
+        (0..64).map(|_| rng.range(0..1000)).collect::<Vec<_>>()
+    };
+    assert_eq!(roll(3), roll(3));
+}
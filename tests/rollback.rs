@@ -0,0 +1,237 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    client::rollback::{Rollback, RollbackHooks},
+    server::{
+        context::{PlayerContext, RoomContext},
+        hooks::{Diff, FrameHooks, FrameInput, GameHooks, RelayHooks},
+        runtime::relay::{RelayRuntime, Settings},
+    },
+    testing::e2e::TestKit,
+};
+
+const ROOM_TYPE: &str = "relay";
+const TIMEOUT: Duration = Duration::from_secs(5);
+const WINDOW: u64 = 4;
+
+// Keeps every simulated frame, so two sessions only compare equal if they ran the same frames
+// with the same inputs.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Walk {
+    frames: Vec<(u64, Vec<(u64, i64)>)>,
+}
+
+impl RollbackHooks for Walk {
+    type State = Walk;
+    type Input = i64;
+
+    fn save_state(&self) -> Self::State {
+        self.clone()
+    }
+
+    fn load_state(&mut self, state: &Self::State) {
+        *self = state.clone();
+    }
+
+    fn advance(&mut self, frame: u64, inputs: &[(u64, Self::Input)]) {
+        self.frames.push((frame, inputs.to_vec()));
+    }
+}
+
+#[test]
+fn mispredicted_inputs_simulate_again_as_if_known() {
+    // Player 2 inputs arrive late, after two frames were predicted
+    let mut late = Rollback::new(Walk::default(), 1, [2], WINDOW);
+    late.advance(1);
+    late.advance(1);
+    assert_eq!(late.receive(2, 0, 5), Some(0));
+    // Predicted from frame 0 once it was simulated again
+    assert_eq!(late.receive(2, 1, 5), None);
+
+    let mut on_time = Rollback::new(Walk::default(), 1, [2], WINDOW);
+    on_time.receive(2, 0, 5);
+    on_time.receive(2, 1, 5);
+    on_time.advance(1);
+    on_time.advance(1);
+
+    assert_eq!(late.game(), on_time.game());
+    assert_eq!(late.confirmed_frame(), 2);
+    assert_eq!(on_time.confirmed_frame(), 2);
+}
+
+#[test]
+fn inputs_older_than_the_confirmed_frame_are_dropped() {
+    let mut rollback = Rollback::new(Walk::default(), 1, [2], WINDOW);
+    rollback.receive(2, 0, 5);
+    rollback.advance(1);
+    assert_eq!(rollback.confirmed_frame(), 1);
+
+    let game = rollback.game().clone();
+    assert_eq!(rollback.receive(2, 0, 9), None);
+    assert_eq!(rollback.game(), &game);
+}
+
+#[test]
+fn players_added_in_the_past_start_within_the_window() {
+    let mut rollback = Rollback::new(Walk::default(), 1, [2], WINDOW);
+    for frame in 0..2 {
+        rollback.receive(2, frame, 5);
+    }
+    while rollback.advance(1).is_some() {}
+    assert_eq!(rollback.confirmed_frame(), 2);
+    assert_eq!(rollback.frame(), 2 + WINDOW);
+
+    // Frames before the confirmed one are never simulated again
+    rollback.add_player(3, 0);
+    let (frame, inputs) = rollback.game().frames.last().unwrap();
+    assert_eq!(*frame, 1 + WINDOW);
+    assert_eq!(inputs.len(), 2);
+
+    rollback.receive(2, 2, 5);
+    assert_eq!(rollback.confirmed_frame(), 2);
+    rollback.receive(3, 2, 7);
+    assert_eq!(rollback.confirmed_frame(), 3);
+    let simulated = rollback
+        .game()
+        .frames
+        .iter()
+        .rev()
+        .find(|(frame, _)| *frame == 2)
+        .unwrap();
+    assert_eq!(simulated.1, vec![(1, 1), (2, 5), (3, 7)]);
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Step {
+    frame: u64,
+    value: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Relayed {
+    server_frame: u64,
+    inputs: Vec<(u64, u64, i64)>,
+}
+
+// Passes every input on to the players of the room.
+struct RelayServer;
+
+impl GameHooks for RelayServer {
+    type Delta = Relayed;
+    type Action = Step;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+impl FrameHooks for RelayServer {
+    type Input = i64;
+
+    fn frame_input(action: Self::Action) -> (u64, Self::Input) {
+        (action.frame, action.value)
+    }
+}
+
+impl RelayHooks for RelayServer {
+    fn on_relay(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        server_frame: u64,
+        inputs: Vec<FrameInput<Self::Input>>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        Some(vec![Diff::All {
+            delta: Relayed {
+                server_frame,
+                inputs: inputs
+                    .into_iter()
+                    .map(|input| (input.player_id, input.frame, input.input))
+                    .collect(),
+            },
+        }])
+    }
+}
+
+#[derive(Default)]
+struct RelayClient {
+    relayed: Vec<Relayed>,
+}
+
+impl thunders::client::core::GameHooks for RelayClient {
+    type Change = Relayed;
+    type Action = Step;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.relayed.push(change);
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+#[tokio::test]
+async fn relay_room_forwards_frames_to_the_other_players() {
+    let kit = TestKit::<Json>::start(|server| {
+        server.register::<RelayRuntime<_>, RelayServer>(ROOM_TYPE, Settings::default())
+    });
+    let first = kit.client(1).await.unwrap();
+    let second = kit.client(2).await.unwrap();
+    first
+        .create::<RelayClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+    second
+        .join::<RelayClient>(ROOM_TYPE, "room", TIMEOUT)
+        .await
+        .unwrap();
+
+    first
+        .action::<RelayClient>(ROOM_TYPE, "room", Step { frame: 3, value: 7 })
+        .unwrap();
+    second.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+
+    let inputs = second.state::<RelayClient, _>(ROOM_TYPE, "room", |client| {
+        client
+            .relayed
+            .iter()
+            .flat_map(|relayed| relayed.inputs.clone())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(inputs, Some(vec![(1, 3, 7)]));
+}
@@ -0,0 +1,115 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    client::{ThundersClientBuilder, protocol::memory::MemoryClientProtocol},
+    server::{
+        ThundersServer,
+        context::{PlayerContext, RoomContext},
+        hooks::{Diff, GameHooks},
+        protocol::memory::MemoryProtocol,
+        runtime::sync::{Settings, SyncRuntime},
+    },
+};
+use tokio::sync::oneshot;
+
+const ROOM_TYPE: &str = "idle";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Nothing;
+
+// Never finishes by itself, only the shutdown ends it.
+struct IdleServer;
+
+impl GameHooks for IdleServer {
+    type Delta = Nothing;
+    type Action = Nothing;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+struct IdleClient;
+
+impl thunders::client::core::GameHooks for IdleClient {
+    type Change = Nothing;
+    type Action = Nothing;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self
+    }
+
+    fn on_change(&mut self, _: Self::Change) {}
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+#[tokio::test]
+async fn shutdown_drains_memory_connections_before_the_timeout() {
+    let protocol = MemoryProtocol::new();
+    // The server stops accepting once every connector is dropped
+    let connector = protocol.connector();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(
+        ThundersServer::new(protocol, Json::default())
+            .register::<SyncRuntime<_>, IdleServer>(ROOM_TYPE, Settings::default())
+            .with_shutdown_timeout(Duration::from_secs(60))
+            .run_until(async {
+                let _ = shutdown_rx.await;
+            }),
+    );
+
+    let client = ThundersClientBuilder::new(
+        MemoryClientProtocol::new(connector.clone()),
+        Json::default(),
+    )
+    .register(ROOM_TYPE)
+    .build()
+    .await
+    .unwrap();
+    client.connect(1, TIMEOUT).await.unwrap();
+    client
+        .create::<IdleClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+
+    shutdown_tx.send(()).unwrap();
+    let result = tokio::time::timeout(TIMEOUT, server)
+        .await
+        .expect("Should drain connections well before the shutdown timeout")
+        .unwrap();
+    assert!(result.is_ok());
+}
@@ -0,0 +1,153 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use thunders::{
+    api::schema::json::Json,
+    client::{
+        ThundersClient, ThundersClientBuilder,
+        error::ThundersClientError,
+        protocol::{tls::ClientTlsConfig, ws::WebSocketClientProtocol},
+    },
+    server::{
+        ThundersServer,
+        context::{PlayerContext, RoomContext},
+        hooks::{Diff, GameHooks},
+        protocol::{tls::ServerTlsConfig, ws::WebSocketProtocol},
+        runtime::sync::{Settings, SyncRuntime},
+    },
+};
+
+const ROOM_TYPE: &str = "silent";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct SilentServer;
+
+impl GameHooks for SilentServer {
+    type Delta = ();
+    type Action = ();
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+struct SilentClient;
+
+impl thunders::client::core::GameHooks for SilentClient {
+    type Change = ();
+    type Action = ();
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self
+    }
+
+    fn on_change(&mut self, _: Self::Change) {}
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+// Serves wss:// on a free port with a self-signed certificate for localhost, returned to be
+// trusted by the clients.
+async fn start_server() -> (u16, CertificateDer<'static>) {
+    let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let certificate = certified_key.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+        certified_key.signing_key.serialize_der(),
+    ));
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let server = ThundersServer::new(
+        WebSocketProtocol::new("127.0.0.1", port)
+            .with_tls(ServerTlsConfig::new(vec![certificate.clone()], key)),
+        Json::default(),
+    )
+    .register::<SyncRuntime<_>, SilentServer>(ROOM_TYPE, Settings::default());
+    tokio::spawn(server.run());
+
+    // Listening once a plain TCP connection goes through
+    tokio::time::timeout(TIMEOUT, async {
+        while tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_err()
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should start listening");
+    (port, certificate)
+}
+
+async fn client(
+    port: u16,
+    tls_config: ClientTlsConfig,
+) -> Result<ThundersClient<Json>, ThundersClientError> {
+    ThundersClientBuilder::new(
+        WebSocketClientProtocol::new("127.0.0.1", port)
+            .with_connect_timeout(TIMEOUT)
+            .with_tls(tls_config.with_server_name("localhost")),
+        Json::default(),
+    )
+    .register(ROOM_TYPE)
+    .build()
+    .await
+}
+
+#[tokio::test]
+async fn connects_when_the_certificate_is_trusted() {
+    let (port, certificate) = start_server().await;
+
+    let tls_config = ClientTlsConfig::new()
+        .with_root_certificate(certificate)
+        .unwrap();
+    let client = client(port, tls_config).await.unwrap();
+    client.connect(1, TIMEOUT).await.unwrap();
+    client
+        .create::<SilentClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn fails_when_the_certificate_is_not_trusted() {
+    let (port, _) = start_server().await;
+
+    assert!(matches!(
+        client(port, ClientTlsConfig::default()).await,
+        Err(ThundersClientError::ConnectionFailure)
+    ));
+}
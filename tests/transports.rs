@@ -0,0 +1,168 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use thunders::{
+    api::schema::json::Json,
+    client::{
+        ThundersClient, ThundersClientBuilder,
+        protocol::{memory::MemoryClientProtocol, uds::UnixSocketClientProtocol},
+    },
+    server::{
+        ThundersServer,
+        context::{PlayerContext, RoomContext},
+        hooks::{Diff, GameHooks},
+        protocol::{memory::MemoryProtocol, uds::UnixSocketProtocol},
+        runtime::sync::{Settings, SyncRuntime},
+    },
+};
+
+const ECHO_TYPE: &str = "echo";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Echoes every action to the whole room.
+struct EchoServer;
+
+impl GameHooks for EchoServer {
+    type Delta = (u64, u32);
+    type Action = u32;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        actions: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        Some(
+            actions
+                .into_iter()
+                .map(|delta| Diff::All { delta })
+                .collect(),
+        )
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+#[derive(Default)]
+struct EchoClient {
+    received: Vec<(u64, u32)>,
+}
+
+impl thunders::client::core::GameHooks for EchoClient {
+    type Change = (u64, u32);
+    type Action = u32;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.received.push(change);
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+async fn wait_received(client: &ThundersClient<Json>, expected: usize) -> Vec<(u64, u32)> {
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            let received = client
+                .active_games
+                .get_as::<EchoClient>(ECHO_TYPE, "room")
+                .unwrap()
+                .map(|view| view.as_ref().received.clone())
+                .unwrap_or_default();
+            if received.len() >= expected {
+                return received;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should receive every echo")
+}
+
+#[tokio::test]
+async fn clients_of_both_transports_share_rooms() {
+    let path =
+        std::env::temp_dir().join(format!("thunders-{}-transports.sock", std::process::id()));
+    let memory = MemoryProtocol::new();
+    let connector = memory.connector();
+    let server = ThundersServer::new((memory, UnixSocketProtocol::new(&path)), Json::default())
+        .register::<SyncRuntime<_>, EchoServer>(
+        ECHO_TYPE,
+        Settings {
+            tick_millis: 10,
+            ..Default::default()
+        },
+    );
+    tokio::spawn(server.run());
+
+    let in_memory =
+        ThundersClientBuilder::new(MemoryClientProtocol::new(connector), Json::default())
+            .register(ECHO_TYPE)
+            .build()
+            .await
+            .unwrap();
+    in_memory.connect(1, TIMEOUT).await.unwrap();
+    in_memory
+        .create::<EchoClient>(ECHO_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+
+    // Retried until the socket is listening
+    let over_socket = tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Ok(client) =
+                ThundersClientBuilder::new(UnixSocketClientProtocol::new(&path), Json::default())
+                    .register(ECHO_TYPE)
+                    .build()
+                    .await
+            {
+                return client;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should reach the socket");
+    over_socket.connect(2, TIMEOUT).await.unwrap();
+    over_socket
+        .join::<EchoClient>(ECHO_TYPE, "room", TIMEOUT)
+        .await
+        .unwrap();
+
+    in_memory
+        .action::<EchoClient>(ECHO_TYPE, "room", 1)
+        .unwrap();
+    wait_received(&over_socket, 1).await;
+    over_socket
+        .action::<EchoClient>(ECHO_TYPE, "room", 2)
+        .unwrap();
+
+    let expected = vec![(1, 1), (2, 2)];
+    assert_eq!(wait_received(&in_memory, 2).await, expected);
+    assert_eq!(wait_received(&over_socket, 2).await, expected);
+}
@@ -0,0 +1,147 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    client::{InternalEvent, prediction::Predicted},
+    server::{
+        context::{PlayerContext, RoomContext},
+        hooks::{ActionHooks, Diff, GameHooks, TurnHooks},
+        runtime::turn::{Settings, TurnRuntime},
+    },
+    testing::e2e::TestKit,
+};
+
+const ROOM_TYPE: &str = "counter";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Add(i64);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Total(i64);
+
+// Players add to the total one after the other, in join order.
+#[derive(Default)]
+struct CounterServer {
+    total: i64,
+    players: Vec<u64>,
+    turn: usize,
+}
+
+impl GameHooks for CounterServer {
+    type Delta = Total;
+    type Action = Add;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        player: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.players.push(player.id());
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+impl ActionHooks for CounterServer {
+    fn on_action(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: u64,
+        action: Self::Action,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        self.total += action.0;
+        self.turn = (self.turn + 1) % self.players.len();
+        Some(vec![Diff::All {
+            delta: Total(self.total),
+        }])
+    }
+}
+
+impl TurnHooks for CounterServer {
+    fn current_turn(&self) -> Option<u64> {
+        self.players.get(self.turn).copied()
+    }
+}
+
+#[derive(Default, Clone)]
+struct Counter {
+    total: i64,
+}
+
+impl thunders::client::core::GameHooks for Counter {
+    type Change = Total;
+    type Action = Add;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.total = change.0;
+    }
+
+    fn on_action(&mut self, action: Self::Action) {
+        self.total += action.0;
+    }
+
+    fn on_finish(self) {}
+}
+
+#[tokio::test]
+async fn out_of_turn_actions_are_rejected_and_undone() {
+    let kit = TestKit::<Json>::start(|server| {
+        server.register::<TurnRuntime<_>, CounterServer>(ROOM_TYPE, Settings::default())
+    });
+    let alice = kit.client(1).await.unwrap();
+    let bob = kit.client(2).await.unwrap();
+    alice
+        .create::<Predicted<Counter>>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+    bob.join::<Predicted<Counter>>(ROOM_TYPE, "room", TIMEOUT)
+        .await
+        .unwrap();
+
+    bob.action::<Predicted<Counter>>(ROOM_TYPE, "room", Add(5))
+        .unwrap();
+    loop {
+        if let InternalEvent::ActionRejected { .. } = bob.next_event().await.unwrap() {
+            break;
+        }
+    }
+    let bob_state =
+        |counter: &Predicted<Counter>| (counter.predicted().total, counter.pending().count());
+    assert_eq!(bob.state(ROOM_TYPE, "room", bob_state), Some((0, 0)));
+
+    alice
+        .action::<Predicted<Counter>>(ROOM_TYPE, "room", Add(3))
+        .unwrap();
+    bob.wait_room_updated(ROOM_TYPE, "room").await.unwrap();
+    assert_eq!(bob.state(ROOM_TYPE, "room", bob_state), Some((3, 0)));
+}
@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::schema::json::Json,
+    client::{ThundersClient, ThundersClientBuilder, protocol::udp::UdpClientProtocol},
+    server::{
+        ThundersServer, ThundersServerResult,
+        context::{PlayerContext, RoomContext},
+        hooks::{Diff, GameHooks},
+        protocol::udp::UdpProtocol,
+        runtime::sync::{Settings, SyncRuntime},
+    },
+};
+use tokio::{net::UdpSocket, sync::oneshot, task::JoinHandle};
+
+const ECHO_TYPE: &str = "echo";
+const WELCOME_SIZE: usize = 5000;
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Kind of the datagrams carrying messages
+const DATA: u8 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Message {
+    Welcome(String),
+    Echo(u32),
+}
+
+// Sends a message spanning several datagrams on join and echoes every action.
+struct EchoServer;
+
+impl GameHooks for EchoServer {
+    type Delta = Message;
+    type Action = Message;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        actions: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        Some(
+            actions
+                .into_iter()
+                .map(|(_, delta)| Diff::All { delta })
+                .collect(),
+        )
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        player: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        Some(vec![Diff::TargetUnique {
+            id: player.id(),
+            delta: Message::Welcome("w".repeat(WELCOME_SIZE)),
+        }])
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+#[derive(Default)]
+struct EchoClient {
+    received: Vec<Message>,
+}
+
+impl thunders::client::core::GameHooks for EchoClient {
+    type Change = Message;
+    type Action = Message;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.received.push(change);
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+#[derive(Clone, Copy)]
+enum Loss {
+    None,
+    // Every fifth datagram is dropped and the others are delayed by a varying amount so they
+    // arrive out of order
+    Lossy,
+    // The first data datagrams sent by the server are dropped
+    FirstFromServer(usize),
+}
+
+// Forwards datagrams between the latest client and the server, so clients restarting behind it
+// reach the server from the same address.
+async fn proxy(server_port: u16, loss: Loss) -> u16 {
+    let front = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+    let back = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+    back.connect(("127.0.0.1", server_port)).await.unwrap();
+    let port = front.local_addr().unwrap().port();
+    let counter = Arc::new(AtomicUsize::new(0));
+    let server_data = Arc::new(AtomicUsize::new(0));
+
+    // Datagrams sent to an address go to the client
+    let forward = move |datagram: Vec<u8>, to: Arc<UdpSocket>, addr: Option<SocketAddr>| {
+        let count = counter.fetch_add(1, Ordering::Relaxed);
+        let delay = match loss {
+            Loss::None => 0,
+            Loss::Lossy if count % 5 == 4 => return,
+            Loss::Lossy => (count % 4) as u64 * 15,
+            Loss::FirstFromServer(dropped) => {
+                if addr.is_some()
+                    && datagram.first() == Some(&DATA)
+                    && server_data.fetch_add(1, Ordering::Relaxed) < dropped
+                {
+                    return;
+                }
+                0
+            }
+        };
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            let _ = match addr {
+                Some(addr) => to.send_to(&datagram, addr).await,
+                None => to.send(&datagram).await,
+            };
+        });
+    };
+
+    tokio::spawn(async move {
+        let mut clients: Vec<SocketAddr> = Vec::new();
+        let mut front_buf = vec![0u8; 65536];
+        let mut back_buf = vec![0u8; 65536];
+        loop {
+            tokio::select! {
+                Ok((len, addr)) = front.recv_from(&mut front_buf) => {
+                    if !clients.contains(&addr) {
+                        clients.push(addr);
+                    }
+                    if clients.last() == Some(&addr) {
+                        forward(front_buf[..len].to_vec(), Arc::clone(&back), None);
+                    }
+                }
+                Ok(len) = back.recv(&mut back_buf) => {
+                    if let Some(addr) = clients.last() {
+                        forward(back_buf[..len].to_vec(), Arc::clone(&front), Some(*addr));
+                    }
+                }
+            }
+        }
+    });
+
+    port
+}
+
+fn start_server(
+    port: u16,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> JoinHandle<ThundersServerResult> {
+    let server = ThundersServer::new(UdpProtocol::new("127.0.0.1", port), Json::default())
+        .register::<SyncRuntime<_>, EchoServer>(
+        ECHO_TYPE,
+        Settings {
+            tick_millis: 10,
+            tick_no_action_millis: 100,
+            ..Default::default()
+        },
+    );
+    tokio::spawn(
+        server
+            .with_shutdown_timeout(Duration::from_secs(60))
+            .run_until(shutdown),
+    )
+}
+
+// The first client creates the room, the others join it.
+async fn client(proxy_port: u16, player_id: u64, create: bool) -> ThundersClient<Json> {
+    let client = ThundersClientBuilder::new(
+        UdpClientProtocol::new("127.0.0.1", proxy_port),
+        Json::default(),
+    )
+    .register(ECHO_TYPE)
+    .build()
+    .await
+    .unwrap();
+    client.connect(player_id, TIMEOUT).await.unwrap();
+    if create {
+        client
+            .create::<EchoClient>(ECHO_TYPE, "room", (), TIMEOUT)
+            .await
+            .unwrap();
+    } else {
+        client
+            .join::<EchoClient>(ECHO_TYPE, "room", TIMEOUT)
+            .await
+            .unwrap();
+    }
+    client
+}
+
+fn free_port() -> u16 {
+    std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+async fn wait_received(client: &ThundersClient<Json>, expected: usize) -> Vec<String> {
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            let received = client
+                .active_games
+                .get_as::<EchoClient>(ECHO_TYPE, "room")
+                .unwrap()
+                .map(|view| {
+                    view.as_ref()
+                        .received
+                        .iter()
+                        .map(|message| format!("{message:?}"))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            if received.len() >= expected {
+                return received;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should receive every message through the lossy proxy")
+}
+
+#[tokio::test]
+async fn delivers_reliable_messages_in_order_through_loss_and_reordering() {
+    let port = free_port();
+    start_server(port, std::future::pending());
+    let client = client(proxy(port, Loss::Lossy).await, 1, true).await;
+
+    for value in 0..50 {
+        client
+            .action::<EchoClient>(ECHO_TYPE, "room", Message::Echo(value))
+            .unwrap();
+    }
+
+    let received = wait_received(&client, 51).await;
+    let expected = std::iter::once(format!("{:?}", Message::Welcome("w".repeat(WELCOME_SIZE))))
+        .chain((0..50).map(|value| format!("{:?}", Message::Echo(value))))
+        .collect::<Vec<_>>();
+    assert_eq!(received, expected);
+}
+
+// The client resends its connection request before receiving anything, which must not acknowledge
+// the lost reply.
+#[tokio::test]
+async fn resends_the_first_reliable_messages_when_lost() {
+    let port = free_port();
+    start_server(port, std::future::pending());
+    let client = client(proxy(port, Loss::FirstFromServer(2)).await, 1, true).await;
+
+    client
+        .action::<EchoClient>(ECHO_TYPE, "room", Message::Echo(0))
+        .unwrap();
+    let received = wait_received(&client, 2).await;
+    assert_eq!(received[1], format!("{:?}", Message::Echo(0)));
+}
+
+#[tokio::test]
+async fn replaces_the_connection_of_a_restarted_client() {
+    let port = free_port();
+    start_server(port, std::future::pending());
+    let proxy_port = proxy(port, Loss::None).await;
+
+    let first = client(proxy_port, 1, true).await;
+    first
+        .action::<EchoClient>(ECHO_TYPE, "room", Message::Echo(0))
+        .unwrap();
+    wait_received(&first, 2).await;
+
+    // Same address and player for the server, only the handshake nonce differs
+    let second = client(proxy_port, 1, false).await;
+    second
+        .action::<EchoClient>(ECHO_TYPE, "room", Message::Echo(1))
+        .unwrap();
+    let received = wait_received(&second, 2).await;
+    assert_eq!(received[1], format!("{:?}", Message::Echo(1)));
+}
+
+#[tokio::test]
+async fn shutdown_drains_connections_before_the_timeout() {
+    let port = free_port();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = start_server(port, async {
+        let _ = shutdown_rx.await;
+    });
+    let _client = client(proxy(port, Loss::Lossy).await, 1, true).await;
+
+    shutdown_tx.send(()).unwrap();
+    let result = tokio::time::timeout(TIMEOUT, server)
+        .await
+        .expect("Should drain connections well before the shutdown timeout")
+        .unwrap();
+    assert!(result.is_ok());
+}
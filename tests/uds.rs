@@ -0,0 +1,242 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+
+use thunders::{
+    api::{
+        framing::{MAX_FRAME_SIZE, read_frame, write_frame},
+        message::InputMessage,
+        schema::{Serialize as _, json::Json},
+    },
+    client::{ThundersClient, ThundersClientBuilder, protocol::uds::UnixSocketClientProtocol},
+    server::{
+        ThundersServer,
+        context::{PlayerContext, RoomContext},
+        handle::ServerHandle,
+        hooks::{Diff, GameHooks},
+        protocol::uds::UnixSocketProtocol,
+        runtime::sync::{Settings, SyncRuntime},
+    },
+};
+use tokio::{io::AsyncWriteExt, net::UnixStream};
+
+const ECHO_TYPE: &str = "echo";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Echoes every action.
+struct EchoServer;
+
+impl GameHooks for EchoServer {
+    type Delta = u32;
+    type Action = u32;
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        actions: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        Some(
+            actions
+                .into_iter()
+                .map(|(_, delta)| Diff::All { delta })
+                .collect(),
+        )
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        _: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+#[derive(Default)]
+struct EchoClient {
+    received: Vec<u32>,
+}
+
+impl thunders::client::core::GameHooks for EchoClient {
+    type Change = u32;
+    type Action = u32;
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.received.push(change);
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+// Unique to the test and the process, so parallel runs never share a socket file.
+fn socket_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("thunders-{}-{name}.sock", std::process::id()))
+}
+
+fn server(path: &PathBuf) -> ThundersServer<UnixSocketProtocol, Json> {
+    ThundersServer::new(UnixSocketProtocol::new(path), Json::default())
+        .register::<SyncRuntime<_>, EchoServer>(
+            ECHO_TYPE,
+            Settings {
+                tick_millis: 10,
+                ..Default::default()
+            },
+        )
+}
+
+fn start_server(path: &PathBuf) -> ServerHandle<Json> {
+    let (handle, server) = server(path).run_with_handle(std::future::pending());
+    tokio::spawn(server);
+    handle
+}
+
+// Retried until the server is listening
+async fn client(path: &PathBuf, player_id: u64) -> ThundersClient<Json> {
+    let client = tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Ok(client) =
+                ThundersClientBuilder::new(UnixSocketClientProtocol::new(path), Json::default())
+                    .register(ECHO_TYPE)
+                    .build()
+                    .await
+            {
+                return client;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should reach the server");
+    client.connect(player_id, TIMEOUT).await.unwrap();
+    client
+}
+
+async fn echo(client: &ThundersClient<Json>, value: u32) -> Vec<u32> {
+    client
+        .action::<EchoClient>(ECHO_TYPE, "room", value)
+        .unwrap();
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            let received = client
+                .active_games
+                .get_as::<EchoClient>(ECHO_TYPE, "room")
+                .unwrap()
+                .map(|view| view.as_ref().received.clone())
+                .unwrap_or_default();
+            if !received.is_empty() {
+                return received;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should receive the echo")
+}
+
+async fn wait_players(handle: &ServerHandle<Json>, expected: &[u64]) {
+    tokio::time::timeout(TIMEOUT, async {
+        while handle.players() != expected {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should reach the expected players");
+}
+
+#[tokio::test]
+async fn messages_round_trip_through_the_socket() {
+    let path = socket_path("round-trip");
+    start_server(&path);
+    let client = client(&path, 1).await;
+    client
+        .create::<EchoClient>(ECHO_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+
+    assert_eq!(echo(&client, 7).await, vec![7]);
+}
+
+#[tokio::test]
+async fn stale_socket_files_are_replaced() {
+    let path = socket_path("stale");
+    // Left behind as a crashed server would
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    start_server(&path);
+    let client = client(&path, 1).await;
+    client
+        .create::<EchoClient>(ECHO_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(echo(&client, 7).await, vec![7]);
+}
+
+#[tokio::test]
+async fn regular_files_are_never_replaced() {
+    let path = socket_path("regular");
+    std::fs::write(&path, "data").unwrap();
+
+    let result = tokio::time::timeout(TIMEOUT, server(&path).run())
+        .await
+        .expect("Should fail to start");
+    assert!(result.is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn frames_over_the_limit_close_the_connection() {
+    let oversized = vec![0u8; MAX_FRAME_SIZE + 1];
+    assert!(write_frame(&mut Vec::new(), &oversized).await.is_err());
+
+    let path = socket_path("oversized");
+    let handle = start_server(&path);
+    let stream = tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Ok(stream) = UnixStream::connect(&path).await {
+                return stream;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should reach the server");
+    let (mut read, mut write) = stream.into_split();
+
+    let connect: InputMessage<'_> = InputMessage::Connect {
+        correlation_id: "connect",
+        id: 1,
+    };
+    write_frame(&mut write, &connect.serialize()).await.unwrap();
+    wait_players(&handle, &[1]).await;
+
+    // Only the length prefix is needed for the server to give up on the frame
+    write.write_u32(MAX_FRAME_SIZE as u32 + 1).await.unwrap();
+    wait_players(&handle, &[]).await;
+    let closed = tokio::time::timeout(TIMEOUT, async {
+        while read_frame(&mut read).await.is_ok() {}
+    })
+    .await;
+    assert!(closed.is_ok());
+}
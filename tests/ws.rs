@@ -0,0 +1,355 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::SinkExt;
+use serde::{Deserialize, Serialize};
+use thunders::{
+    api::{
+        message::InputMessage,
+        schema::{Serialize as _, json::Json},
+    },
+    client::{
+        ThundersClient, ThundersClientBuilder, error::ThundersClientError,
+        protocol::ws::WebSocketClientProtocol,
+    },
+    server::{
+        ThundersServer,
+        context::{PlayerContext, RoomContext},
+        handle::ServerHandle,
+        hooks::{Diff, GameHooks},
+        protocol::ws::{HandshakeRejection, WebSocketProtocol},
+        runtime::sync::{Settings, SyncRuntime},
+    },
+};
+use tokio_tungstenite::tungstenite::{
+    Error, Message, client::IntoClientRequest, http::header::SEC_WEBSOCKET_PROTOCOL,
+};
+
+const ROOM_TYPE: &str = "lobby";
+const TIMEOUT: Duration = Duration::from_secs(5);
+const TEAPOT: u16 = 418;
+const SUBPROTOCOL: &str = "thunders.v1";
+
+// What the room learnt about the connection of the player joining it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Welcome {
+    path: Option<String>,
+    team: Option<String>,
+    client: Option<String>,
+    token: Option<String>,
+}
+
+struct LobbyServer;
+
+impl GameHooks for LobbyServer {
+    type Delta = Welcome;
+    type Action = ();
+    type Options = ();
+    type Event = ();
+
+    fn build(_: Self::Options) -> Self {
+        Self
+    }
+
+    fn on_tick(
+        &mut self,
+        _: &mut RoomContext,
+        _: &HashMap<u64, Arc<PlayerContext>>,
+        _: Vec<(u64, Self::Action)>,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        None
+    }
+
+    fn on_join(
+        &mut self,
+        _: &mut RoomContext,
+        player: &PlayerContext,
+    ) -> Option<Vec<Diff<Self::Delta>>> {
+        let metadata = player.metadata();
+        Some(vec![Diff::TargetUnique {
+            id: player.id(),
+            delta: Welcome {
+                path: metadata.path.clone(),
+                team: metadata.query_param("team").map(str::to_string),
+                client: metadata.header("X-Client").map(str::to_string),
+                token: player.attr("token").map(str::to_string),
+            },
+        }])
+    }
+
+    fn on_leave(&mut self, _: &mut RoomContext, _: &PlayerContext) -> Option<Diff<Self::Delta>> {
+        None
+    }
+
+    fn is_finished(&self) -> (bool, Option<Diff<Self::Delta>>) {
+        (false, None)
+    }
+}
+
+#[derive(Default)]
+struct LobbyClient {
+    welcome: Option<Welcome>,
+}
+
+impl thunders::client::core::GameHooks for LobbyClient {
+    type Change = Welcome;
+    type Action = ();
+    type Options = ();
+
+    fn build(_: &Self::Options) -> Self {
+        Self::default()
+    }
+
+    fn on_change(&mut self, change: Self::Change) {
+        self.welcome = Some(change);
+    }
+
+    fn on_action(&mut self, _: Self::Action) {}
+
+    fn on_finish(self) {}
+}
+
+// Only upgrades on /play with the token "secret", which is handed to the rooms as an attribute.
+// Speaks the "thunders.v1" subprotocol.
+async fn start_server() -> u16 {
+    start_server_with(|protocol| protocol).await.0
+}
+
+async fn start_server_with(
+    configure: impl FnOnce(WebSocketProtocol) -> WebSocketProtocol,
+) -> (u16, ServerHandle<Json>) {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let protocol = WebSocketProtocol::new("127.0.0.1", port)
+        .with_subprotocol(SUBPROTOCOL)
+        .with_handshake(|metadata| {
+            if metadata.path.as_deref() != Some("/play") {
+                return Err(HandshakeRejection::new(TEAPOT, "Not a game endpoint"));
+            }
+            match metadata.query_param("token") {
+                Some(token) if token == "secret" => {
+                    Ok(HashMap::from([("token".to_string(), token.to_string())]))
+                }
+                _ => Err(HandshakeRejection::unauthorized("Invalid token")),
+            }
+        });
+    let (handle, server) = ThundersServer::new(configure(protocol), Json::default())
+        .register::<SyncRuntime<_>, LobbyServer>(ROOM_TYPE, Settings::default())
+        .run_with_handle(std::future::pending());
+    tokio::spawn(server);
+
+    // Listening once a plain TCP connection goes through
+    tokio::time::timeout(TIMEOUT, async {
+        while tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_err()
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should start listening");
+    (port, handle)
+}
+
+async fn build(
+    protocol: WebSocketClientProtocol,
+) -> Result<ThundersClient<Json>, ThundersClientError> {
+    ThundersClientBuilder::new(protocol, Json::default())
+        .register(ROOM_TYPE)
+        .build()
+        .await
+}
+
+// Status the upgrade was answered with
+async fn handshake_status(url: String) -> u16 {
+    match tokio_tungstenite::connect_async(url).await {
+        Ok((_, response)) => response.status().as_u16(),
+        Err(Error::Http(response)) => response.status().as_u16(),
+        Err(err) => panic!("Should get an HTTP answer, got {err}"),
+    }
+}
+
+async fn welcome(client: &ThundersClient<Json>) -> Welcome {
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            let welcome = client
+                .active_games
+                .get_as::<LobbyClient>(ROOM_TYPE, "room")
+                .unwrap()
+                .and_then(|view| view.as_ref().welcome.clone());
+            if let Some(welcome) = welcome {
+                return welcome;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should be welcomed by the room")
+}
+
+#[tokio::test]
+async fn rejected_handshakes_get_the_configured_status() {
+    let port = start_server().await;
+
+    assert_eq!(
+        handshake_status(format!("ws://127.0.0.1:{port}/play?token=wrong")).await,
+        401
+    );
+    assert_eq!(
+        handshake_status(format!("ws://127.0.0.1:{port}/admin?token=secret")).await,
+        TEAPOT
+    );
+    assert_eq!(
+        handshake_status(format!("ws://127.0.0.1:{port}/play?token=secret")).await,
+        101
+    );
+}
+
+#[tokio::test]
+async fn request_metadata_reaches_the_player_context() {
+    let port = start_server().await;
+
+    let client = build(
+        WebSocketClientProtocol::from_url(format!(
+            "ws://127.0.0.1:{port}/play?team=red&token=secret"
+        ))
+        .with_header("X-Client", "tests"),
+    )
+    .await
+    .unwrap();
+    client.connect(1, TIMEOUT).await.unwrap();
+    client
+        .create::<LobbyClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        welcome(&client).await,
+        Welcome {
+            path: Some("/play".to_string()),
+            team: Some("red".to_string()),
+            client: Some("tests".to_string()),
+            token: Some("secret".to_string()),
+        }
+    );
+}
+
+#[tokio::test]
+async fn supported_subprotocols_are_negotiated() {
+    let port = start_server().await;
+    let url = format!("ws://127.0.0.1:{port}/play?token=secret");
+
+    // The first offered subprotocol the server supports is selected
+    let mut request = url.as_str().into_client_request().unwrap();
+    request.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        "thunders.v2, thunders.v1".parse().unwrap(),
+    );
+    let (_, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+    assert_eq!(
+        response.headers().get(SEC_WEBSOCKET_PROTOCOL).unwrap(),
+        SUBPROTOCOL
+    );
+
+    let client = build(
+        WebSocketClientProtocol::from_url(url.as_str())
+            .with_subprotocol("thunders.v2")
+            .with_subprotocol(SUBPROTOCOL),
+    )
+    .await
+    .unwrap();
+    client.connect(1, TIMEOUT).await.unwrap();
+    client
+        .create::<LobbyClient>(ROOM_TYPE, "room", (), TIMEOUT)
+        .await
+        .unwrap();
+
+    // Nothing in common, the client refuses the upgrade
+    assert!(matches!(
+        build(WebSocketClientProtocol::from_url(url.as_str()).with_subprotocol("thunders.v2"))
+            .await,
+        Err(ThundersClientError::ConnectionFailure)
+    ));
+}
+
+#[tokio::test]
+async fn connecting_gives_up_after_the_connect_timeout() {
+    // Accepted by the kernel backlog, the upgrade request is never answered
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let started = Instant::now();
+    let result = tokio::time::timeout(
+        TIMEOUT,
+        build(
+            WebSocketClientProtocol::new("127.0.0.1", port)
+                .with_connect_timeout(Duration::from_millis(200)),
+        ),
+    )
+    .await
+    .expect("Should give up before the test timeout");
+    assert!(matches!(
+        result,
+        Err(ThundersClientError::ConnectionFailure)
+    ));
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    drop(listener);
+}
+
+// Ids of the connected players, in increasing order
+async fn wait_players(handle: &ServerHandle<Json>, expected: &[u64]) {
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            let mut players = handle.players();
+            players.sort();
+            if players == expected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Should reach the expected players");
+}
+
+#[tokio::test]
+async fn idle_clients_are_disconnected() {
+    let idle_timeout = Duration::from_millis(300);
+    let (port, handle) = start_server_with(|protocol| {
+        protocol.with_heartbeat(Duration::from_millis(50), idle_timeout)
+    })
+    .await;
+    let url = format!("ws://127.0.0.1:{port}/play?token=secret");
+
+    // Pings from the client keep it connected past the idle timeout
+    let alive = build(
+        WebSocketClientProtocol::from_url(url.as_str())
+            .with_heartbeat(Duration::from_millis(50), Duration::from_secs(5)),
+    )
+    .await
+    .unwrap();
+    alive.connect(1, TIMEOUT).await.unwrap();
+
+    // Never read from again, so the pings of the server are not even answered
+    let (mut idle, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    let connect: InputMessage<'_> = InputMessage::Connect {
+        correlation_id: "connect",
+        id: 2,
+    };
+    idle.send(Message::Text(
+        String::from_utf8(connect.serialize()).unwrap().into(),
+    ))
+    .await
+    .unwrap();
+    wait_players(&handle, &[1, 2]).await;
+
+    tokio::time::sleep(idle_timeout).await;
+    wait_players(&handle, &[1]).await;
+}